@@ -1,4 +1,13 @@
 #import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip}
+#import bevy_pbr::mesh_view_bindings::view
+
+struct ParticleShading {
+    fog_color: vec4<f32>,
+    fog_density: f32,
+    edge_softness: f32,
+};
+
+@group(3) @binding(0) var<uniform> shading: ParticleShading;
 
 struct Vertex {
     @location(0) position: vec3<f32>,
@@ -7,17 +16,39 @@ struct Vertex {
 
     @location(3) i_pos_scale: vec4<f32>,
     @location(4) i_color: vec4<f32>,
+    @location(5) i_rotation: vec4<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(2) fog: f32,
+#ifdef POINTS
+    @location(1) uv: vec2<f32>,
+#endif
 };
 
+// Rotate `v` by the unit quaternion `q`.
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
-    let position = vertex.position * vertex.i_pos_scale.w + vertex.i_pos_scale.xyz;
     var out: VertexOutput;
+#ifdef POINTS
+    // Turn the quad towards the camera by spanning it along the camera's
+    // right and up axes.
+    let right = view.world_from_view[0].xyz;
+    let up = view.world_from_view[1].xyz;
+    let offset = right * vertex.position.x + up * vertex.position.y;
+    let position = offset * vertex.i_pos_scale.w + vertex.i_pos_scale.xyz;
+    out.uv = vertex.uv;
+#else
+    let rotated = rotate(vertex.i_rotation, vertex.position);
+    let position = rotated * vertex.i_pos_scale.w + vertex.i_pos_scale.xyz;
+#endif
     // NOTE: Passing 0 as the instance_index to get_world_from_local() is a hack
     // for this example as the instance_index builtin would map to the wrong
     // index in the Mesh array. This index could be passed in via another
@@ -27,10 +58,37 @@ fn vertex(vertex: Vertex) -> VertexOutput {
         vec4<f32>(position, 1.0)
     );
     out.color = vertex.i_color;
+
+    // Fog is computed once per instance from the depth of its center, so
+    // every vertex of a particle gets the same amount.
+    let view_position = view.view_from_world * vec4<f32>(vertex.i_pos_scale.xyz, 1.0);
+    let depth = max(-view_position.z, 0.0);
+    out.fog = 1.0 - exp(-shading.fog_density * depth);
     return out;
 }
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+#ifdef POINTS
+    // Distance to the center of the quad, 1 at the edge of the inscribed
+    // circle.
+    let d = length(in.uv - vec2<f32>(0.5)) * 2.0;
+
+    // Either a glow that falls off towards the edge, or a disc that fades
+    // out over the soft edge. The edge is at least a pixel wide, so it
+    // doesn't alias. With additive blending the alpha channel only scales
+    // the contribution.
+    var glow = pow(clamp(1.0 - d, 0.0, 1.0), 2.0);
+    if shading.edge_softness > 0.0 {
+        let edge = max(shading.edge_softness, fwidth(d));
+        glow = 1.0 - smoothstep(1.0 - edge, 1.0, d);
+    }
+    // Points are added on top of whatever is behind them, so they fade out
+    // instead of towards the fog color. With the default fog color that
+    // looks the same.
+    let visible = 1.0 - in.fog;
+    return vec4<f32>(in.color.rgb * in.color.a * glow * visible, glow);
+#else
+    return vec4<f32>(mix(in.color.rgb, shading.fog_color.rgb, in.fog), in.color.a);
+#endif
 }
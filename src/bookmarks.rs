@@ -0,0 +1,191 @@
+use std::{fmt::Write, path::PathBuf};
+
+use bevy::prelude::*;
+
+/// File the bookmarks are persisted to, relative to the working directory.
+const BOOKMARKS_FILE: &str = "camera_bookmarks.txt";
+
+/// Time it takes to move the camera to a bookmark.
+const TRANSITION_SECS: f32 = 0.8;
+
+/// How long the confirmation message stays on screen.
+const MESSAGE_SECS: f32 = 1.5;
+
+const KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Saved camera viewpoints.
+///
+/// `Ctrl+1` to `Ctrl+9` save the current camera transform and `1` to `9`
+/// move the camera back to it. The bookmarks are written to
+/// [`BOOKMARKS_FILE`] so they survive restarts.
+#[derive(Resource)]
+pub struct CameraBookmarks {
+    path: PathBuf,
+    slots: [Option<Transform>; 9],
+    transition: Option<Transition>,
+}
+
+struct Transition {
+    from: Transform,
+    to: Transform,
+    elapsed: f32,
+}
+
+impl CameraBookmarks {
+    /// Load the bookmarks saved in an earlier session, if any.
+    pub fn load() -> Self {
+        let path = PathBuf::from(BOOKMARKS_FILE);
+        let mut slots = [None; 9];
+
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            for line in text.lines() {
+                match parse_line(line) {
+                    Some((slot, transform)) => slots[slot] = Some(transform),
+                    None => eprintln!("Ignoring invalid camera bookmark: {line}"),
+                }
+            }
+        }
+
+        Self {
+            path,
+            slots,
+            transition: None,
+        }
+    }
+
+    fn save(&self) {
+        let mut text = String::new();
+        for (slot, transform) in self.slots.iter().enumerate() {
+            let Some(Transform {
+                translation: t,
+                rotation: r,
+                ..
+            }) = transform
+            else {
+                continue;
+            };
+            let _ = writeln!(
+                text,
+                "{} {} {} {} {} {} {} {}",
+                slot + 1,
+                t.x,
+                t.y,
+                t.z,
+                r.x,
+                r.y,
+                r.z,
+                r.w
+            );
+        }
+
+        if let Err(e) = std::fs::write(&self.path, text) {
+            eprintln!("Could not save camera bookmarks: {e}");
+        }
+    }
+}
+
+/// Parse a `slot tx ty tz rx ry rz rw` line, with slots numbered from 1.
+fn parse_line(line: &str) -> Option<(usize, Transform)> {
+    let mut words = line.split_whitespace();
+    let slot: usize = words.next()?.parse().ok()?;
+    let slot = slot.checked_sub(1).filter(|&s| s < 9)?;
+
+    let mut numbers = [0.0; 7];
+    for n in &mut numbers {
+        *n = words.next()?.parse().ok()?;
+    }
+    let [tx, ty, tz, rx, ry, rz, rw] = numbers;
+
+    let transform = Transform {
+        translation: Vec3::new(tx, ty, tz),
+        rotation: Quat::from_xyzw(rx, ry, rz, rw).normalize(),
+        ..default()
+    };
+    Some((slot, transform))
+}
+
+#[derive(Component)]
+pub struct BookmarkMessage(Timer);
+
+pub fn bookmark_message_setup(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextColor(Color::from(Srgba::WHITE)),
+        Node {
+            position_type: PositionType::Absolute,
+            left: px(8),
+            bottom: px(8),
+            ..default()
+        },
+        Visibility::Hidden,
+        BookmarkMessage(Timer::from_seconds(MESSAGE_SECS, TimerMode::Once)),
+        Pickable::IGNORE,
+    ));
+}
+
+pub fn camera_bookmarks(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut camera: Single<&mut Transform, With<Camera>>,
+    mut message: Single<(&mut Text, &mut Visibility, &mut BookmarkMessage)>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    for (slot, key) in KEYS.iter().enumerate() {
+        if !keys.just_pressed(*key) {
+            continue;
+        }
+
+        if ctrl {
+            bookmarks.slots[slot] = Some(**camera);
+            bookmarks.save();
+
+            let (text, visibility, timer) = &mut *message;
+            text.0 = format!("Saved camera bookmark {}", slot + 1);
+            **visibility = Visibility::Inherited;
+            timer.0.reset();
+        } else if let Some(to) = bookmarks.slots[slot] {
+            bookmarks.transition = Some(Transition {
+                from: **camera,
+                to,
+                elapsed: 0.0,
+            });
+        }
+    }
+
+    let (_, visibility, timer) = &mut *message;
+    timer.0.tick(time.delta());
+    if timer.0.just_finished() {
+        **visibility = Visibility::Hidden;
+    }
+
+    let Some(transition) = &mut bookmarks.transition else {
+        return;
+    };
+
+    transition.elapsed += time.delta_secs();
+    let t = (transition.elapsed / TRANSITION_SECS).min(1.0);
+    let t = t * t * (3.0 - 2.0 * t);
+
+    // Interpolate the orientation and the distance to the orbit target
+    // separately, so the camera keeps looking at the target on the way.
+    let from_distance = transition.from.translation.length();
+    let to_distance = transition.to.translation.length();
+    camera.rotation = transition.from.rotation.slerp(transition.to.rotation, t);
+    camera.translation = -camera.forward() * from_distance.lerp(to_distance, t);
+
+    if t >= 1.0 {
+        bookmarks.transition = None;
+    }
+}
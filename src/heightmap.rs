@@ -0,0 +1,82 @@
+use std::{path::Path, sync::OnceLock};
+
+use bevy::{
+    asset::RenderAssetUsages,
+    color::{Color, LinearRgba},
+    image::{CompressedImageFormats, Image, ImageSampler, ImageType},
+    math::{FloatExt, Vec2},
+};
+
+/// The heightmap passed with `--heightmap`, if any.
+pub static HEIGHTMAP: OnceLock<Heightmap> = OnceLock::new();
+
+/// Terrain heights read from the red channel of an image.
+///
+/// The image lies in the XZ plane, centered on the origin, with X going
+/// right along the image and Z going down along it. Every pixel covers
+/// `cell_size` units, and a white pixel is `max_height` units high.
+pub struct Heightmap {
+    width: u32,
+    height: u32,
+    cell_size: f32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn load(path: &Path, cell_size: f32, max_height: f32) -> Result<Self, String> {
+        if cell_size <= 0.0 {
+            return Err(format!("cell size must be positive, got {cell_size}"));
+        }
+
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+        let image = Image::from_buffer(
+            &bytes,
+            ImageType::Extension(extension),
+            CompressedImageFormats::NONE,
+            false,
+            ImageSampler::Default,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let (width, height) = (image.width(), image.height());
+        let mut heights = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let color: Color = image.get_color_at(x, y).map_err(|e| e.to_string())?;
+                heights.push(LinearRgba::from(color).red * max_height);
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            cell_size,
+            heights,
+        })
+    }
+
+    fn at(&self, x: u32, y: u32) -> f32 {
+        self.heights[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Bilinearly interpolated height at `x`, `z`.
+    ///
+    /// Points beyond the edges of the image get the height of the nearest
+    /// edge.
+    pub fn sample(&self, x: f32, z: f32) -> f32 {
+        let max = Vec2::new(self.width as f32 - 1.0, self.height as f32 - 1.0);
+        let g = (Vec2::new(x, z) / self.cell_size + max / 2.0).clamp(Vec2::ZERO, max);
+
+        // The upper corner is clamped too, for images only one pixel wide
+        // or high and for points on the far edges.
+        let i = g.floor().as_uvec2();
+        let j = (i + 1).min(max.as_uvec2());
+        let f = g - i.as_vec2();
+
+        let top = self.at(i.x, i.y).lerp(self.at(j.x, i.y), f.x);
+        let bottom = self.at(i.x, j.y).lerp(self.at(j.x, j.y), f.x);
+        top.lerp(bottom, f.y)
+    }
+}
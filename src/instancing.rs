@@ -13,6 +13,7 @@ use bevy::{
     render::{
         Render, RenderApp, RenderStartup, RenderSystems,
         extract_component::{ExtractComponent, ExtractComponentPlugin},
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
         mesh::{RenderMesh, RenderMeshBufferInfo, allocator::MeshAllocator},
         render_asset::RenderAssets,
         render_phase::{
@@ -20,7 +21,7 @@ use bevy::{
             RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
         },
         render_resource::*,
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         sync_world::MainEntity,
         view::ExtractedView,
     },
@@ -42,20 +43,62 @@ impl ExtractComponent for InstanceMaterialData {
     }
 }
 
+/// How the particle instances are drawn.
+#[derive(Resource, ExtractResource, Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub enum ParticleRenderMode {
+    /// Opaque meshed spheres.
+    #[default]
+    Spheres,
+    /// Camera-facing quads drawn as glowing points with additive blending.
+    ///
+    /// The quads are sized in world units, so their size on screen attenuates
+    /// with the distance to the camera. This expects the instanced mesh to be
+    /// a unit quad in the XY plane.
+    Points,
+}
+
+/// Shading options shared by all particle instances.
+#[derive(Resource, ExtractResource, ShaderType, Clone, Copy, Default, PartialEq, Debug)]
+pub struct ParticleShading {
+    /// Linear RGBA color that distant particles fade towards.
+    pub fog_color: Vec4,
+    /// How quickly the fog thickens with depth. A density of 0 turns it off.
+    ///
+    /// Each instance gets a single fog factor from the view-space depth of
+    /// its center, `1 - exp(-density * depth)`, so fog is cheap and a
+    /// particle fades as a whole.
+    pub fog_density: f32,
+    /// Width of the soft edge of points, as a fraction of their radius.
+    ///
+    /// With a softness of 0, points are drawn as glows that fade out towards
+    /// their edge. Otherwise they are drawn as solid discs whose edge fades
+    /// out over this width, and over at least a pixel to avoid jagged edges.
+    /// Spheres are not affected.
+    pub edge_softness: f32,
+}
+
 pub struct CustomMaterialPlugin;
 
 impl Plugin for CustomMaterialPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ExtractComponentPlugin::<InstanceMaterialData>::default());
+        app.init_resource::<ParticleRenderMode>();
+        app.init_resource::<ParticleShading>();
+        app.add_plugins((
+            ExtractComponentPlugin::<InstanceMaterialData>::default(),
+            ExtractResourcePlugin::<ParticleRenderMode>::default(),
+            ExtractResourcePlugin::<ParticleShading>::default(),
+        ));
         app.sub_app_mut(RenderApp)
             .add_render_command::<Transparent3d, DrawCustom>()
             .init_resource::<SpecializedMeshPipelines<CustomPipeline>>()
+            .init_resource::<ParticleShadingBindGroup>()
             .add_systems(RenderStartup, init_custom_pipeline)
             .add_systems(
                 Render,
                 (
                     queue_custom.in_set(RenderSystems::QueueMeshes),
                     prepare_instance_buffers.in_set(RenderSystems::PrepareResources),
+                    prepare_shading_bind_group.in_set(RenderSystems::PrepareBindGroups),
                 ),
             );
     }
@@ -67,6 +110,8 @@ pub struct InstanceData {
     pub position: Vec3,
     pub scale: f32,
     pub color: [f32; 4],
+    /// Orientation as a quaternion in `x, y, z, w` order.
+    pub rotation: [f32; 4],
 }
 
 fn queue_custom(
@@ -79,6 +124,7 @@ fn queue_custom(
     material_meshes: Query<(Entity, &MainEntity), With<InstanceMaterialData>>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
     views: Query<(&ExtractedView, &Msaa)>,
+    render_mode: Res<ParticleRenderMode>,
 ) {
     let draw_custom = transparent_3d_draw_functions.read().id::<DrawCustom>();
 
@@ -103,7 +149,12 @@ fn queue_custom(
             let key =
                 view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
             let pipeline = pipelines
-                .specialize(&pipeline_cache, &custom_pipeline, key, &mesh.layout)
+                .specialize(
+                    &pipeline_cache,
+                    &custom_pipeline,
+                    (key, *render_mode),
+                    &mesh.layout,
+                )
                 .unwrap();
             transparent_phase.add(Transparent3d {
                 entity: (entity, *main_entity),
@@ -142,34 +193,84 @@ fn prepare_instance_buffers(
     }
 }
 
+/// The uniform buffer with the [`ParticleShading`] and its bind group, kept
+/// across frames since the shading rarely changes.
+#[derive(Resource, Default)]
+pub struct ParticleShadingBindGroup {
+    buffer: UniformBuffer<ParticleShading>,
+    bind_group: Option<BindGroup>,
+}
+
+fn prepare_shading_bind_group(
+    shading: Res<ParticleShading>,
+    mut shading_bind_group: ResMut<ParticleShadingBindGroup>,
+    custom_pipeline: Res<CustomPipeline>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let ParticleShadingBindGroup { buffer, bind_group } = &mut *shading_bind_group;
+    if buffer.buffer().is_some() && buffer.get() == &*shading {
+        return;
+    }
+
+    let old_buffer = buffer.buffer().map(Buffer::id);
+    buffer.set(*shading);
+    buffer.write_buffer(&render_device, &render_queue);
+
+    // Writing only reallocates the buffer the first time, after which the
+    // bind group can be kept.
+    if buffer.buffer().map(Buffer::id) != old_buffer
+        && let Some(binding) = buffer.binding()
+    {
+        *bind_group = Some(render_device.create_bind_group(
+            "particle shading bind group",
+            &custom_pipeline.shading_layout,
+            &BindGroupEntries::single(binding),
+        ));
+    }
+}
+
 #[derive(Resource)]
 pub struct CustomPipeline {
     shader: Handle<Shader>,
     mesh_pipeline: MeshPipeline,
+    shading_layout: BindGroupLayout,
 }
 
 fn init_custom_pipeline(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mesh_pipeline: Res<MeshPipeline>,
+    render_device: Res<RenderDevice>,
 ) {
     let shader = Shader::from_wgsl(SHADER, file!());
+    let shading_layout = render_device.create_bind_group_layout(
+        "particle shading layout",
+        &BindGroupLayoutEntries::single(
+            ShaderStages::VERTEX_FRAGMENT,
+            binding_types::uniform_buffer::<ParticleShading>(false),
+        ),
+    );
     commands.insert_resource(CustomPipeline {
         shader: asset_server.add(shader),
         mesh_pipeline: mesh_pipeline.clone(),
+        shading_layout,
     });
 }
 
 impl SpecializedMeshPipeline for CustomPipeline {
-    type Key = MeshPipelineKey;
+    type Key = (MeshPipelineKey, ParticleRenderMode);
 
     fn specialize(
         &self,
-        key: Self::Key,
+        (key, render_mode): Self::Key,
         layout: &MeshVertexBufferLayoutRef,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
         let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
 
+        // The mesh pipeline uses bind groups 0 to 2, so the shading options
+        // go after them.
+        descriptor.layout.push(self.shading_layout.clone());
         descriptor.vertex.shader = self.shader.clone();
         descriptor.vertex.buffers.push(VertexBufferLayout {
             array_stride: size_of::<InstanceData>() as u64,
@@ -185,9 +286,42 @@ impl SpecializedMeshPipeline for CustomPipeline {
                     offset: VertexFormat::Float32x4.size(),
                     shader_location: 4,
                 },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 2 * VertexFormat::Float32x4.size(),
+                    shader_location: 5,
+                },
             ],
         });
-        descriptor.fragment.as_mut().unwrap().shader = self.shader.clone();
+        let fragment = descriptor.fragment.as_mut().unwrap();
+        fragment.shader = self.shader.clone();
+
+        if render_mode == ParticleRenderMode::Points {
+            descriptor.vertex.shader_defs.push("POINTS".into());
+            fragment.shader_defs.push("POINTS".into());
+
+            // Points only ever add light, so they don't need to be sorted and
+            // shouldn't occlude each other.
+            if let Some(Some(target)) = fragment.targets.first_mut() {
+                target.blend = Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent::OVER,
+                });
+            }
+            if let Some(depth_stencil) = &mut descriptor.depth_stencil {
+                depth_stencil.depth_write_enabled = false;
+            }
+        } else if let Some(Some(target)) = fragment.targets.first_mut() {
+            // Spheres blend on their alpha so they can fade out. Instances are
+            // not sorted by depth, so a translucent sphere can hide the ones
+            // drawn after it.
+            target.blend = Some(BlendState::ALPHA_BLENDING);
+        }
+
         Ok(descriptor)
     }
 }
@@ -197,9 +331,33 @@ pub type DrawCustom = (
     SetMeshViewBindGroup<0>,
     SetMeshViewBindingArrayBindGroup<1>,
     SetMeshBindGroup<2>,
+    SetParticleShadingBindGroup<3>,
     DrawMeshInstanced,
 );
 
+pub struct SetParticleShadingBindGroup<const I: usize>;
+
+impl<const I: usize, P: PhaseItem> RenderCommand<P> for SetParticleShadingBindGroup<I> {
+    type Param = SRes<ParticleShadingBindGroup>;
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        _entity: Option<()>,
+        bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = &bind_group.into_inner().bind_group else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_bind_group(I, bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
 pub struct DrawMeshInstanced;
 
 impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
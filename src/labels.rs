@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::Particles;
+
+/// Maximum number of particles that get a label.
+const MAX_LABELS: usize = 16;
+
+/// Whether the first [`MAX_LABELS`] particles are labelled with their id and
+/// position. Toggled with `L`.
+#[derive(Resource, Default)]
+pub struct ParticleLabels {
+    pub enabled: bool,
+}
+
+#[derive(Component)]
+pub struct ParticleLabel(usize);
+
+pub fn particle_labels_setup(mut commands: Commands) {
+    for i in 0..MAX_LABELS {
+        commands.spawn((
+            Text::default(),
+            TextFont::from_font_size(12.0),
+            TextColor(Color::from(Srgba::WHITE)),
+            Node {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            Visibility::Hidden,
+            ParticleLabel(i),
+            Pickable::IGNORE,
+        ));
+    }
+}
+
+pub fn toggle_particle_labels(keys: Res<ButtonInput<KeyCode>>, mut labels: ResMut<ParticleLabels>) {
+    if keys.just_pressed(KeyCode::KeyL) {
+        labels.enabled = !labels.enabled;
+    }
+}
+
+pub fn update_particle_labels(
+    labels: Res<ParticleLabels>,
+    particles: Single<&Particles>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    mut query: Query<(&ParticleLabel, &mut Text, &mut Node, &mut Visibility)>,
+) {
+    let (camera, camera_transform) = *camera;
+
+    for (label, mut text, mut node, mut visibility) in &mut query {
+        let particle = particles.0.get(label.0).filter(|_| labels.enabled);
+        let Some(p) = particle.map(|p| &p.particle) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        // Particles behind the camera have no position on screen.
+        let Ok(screen) = camera.world_to_viewport(camera_transform, p.pos) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        text.0 = format!("#{} ({:.1}, {:.1}, {:.1})", p.id, p.pos.x, p.pos.y, p.pos.z);
+        node.left = px(screen.x);
+        node.top = px(screen.y);
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -1,4 +1,11 @@
-use std::{path::Path, sync::Mutex, time::Instant};
+use std::{
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
 
 use bevy::{
     camera::visibility::NoFrustumCulling,
@@ -6,20 +13,161 @@ use bevy::{
     input::mouse::{AccumulatedMouseMotion, MouseWheel},
     prelude::*,
     render::view::NoIndirectDrawing,
+    window::PrimaryWindow,
+};
+use bookmarks::{CameraBookmarks, bookmark_message_setup, camera_bookmarks};
+use heightmap::{HEIGHTMAP, Heightmap};
+use instancing::{
+    CustomMaterialPlugin, InstanceData, InstanceMaterialData, ParticleRenderMode, ParticleShading,
+};
+use labels::{
+    ParticleLabels, particle_labels_setup, toggle_particle_labels, update_particle_labels,
 };
-use instancing::{CustomMaterialPlugin, InstanceData, InstanceMaterialData};
+use mouse_force::{MouseForce, apply_mouse_force, toggle_mouse_force};
+use rand::{SeedableRng, rngs::StdRng};
 use roto::Val;
-use script_manager::ScriptManager;
+use script_manager::{RNG, STEP_EMIT_COUNT, ScriptManager};
+use sdf::{SDF, Sdf};
+use spatial_hash::SpatialHash;
+use timeline::{Timeline, play_timeline};
 
+mod bookmarks;
+mod heightmap;
 mod instancing;
+mod labels;
+mod mouse_force;
+mod noise;
 mod script_manager;
+mod sdf;
+mod spatial_hash;
+mod timeline;
+
+/// Command line arguments.
+struct Args {
+    script: Option<PathBuf>,
+    /// Scripts to play instead of `script`, see [`Timeline`].
+    timeline: Option<PathBuf>,
+    /// Start the timeline over after its last script.
+    timeline_loop: bool,
+    sdf: Option<PathBuf>,
+    heightmap: Option<PathBuf>,
+    /// Size of a heightmap pixel in world units.
+    heightmap_cell: f32,
+    /// Height of a white heightmap pixel in world units.
+    heightmap_height: f32,
+    /// Run `add` every rendered frame instead of at the fixed rate.
+    frame_add: bool,
+    /// Run `update` every rendered frame instead of at the fixed rate.
+    frame_update: bool,
+    /// Start with the camera locked, see [`CameraLock`].
+    lock_camera: bool,
+}
+
+impl Args {
+    const USAGE: &'static str = "usage: roto-demo [SCRIPT] [--timeline FILE] [--timeline-loop] \
+        [--sdf FILE] [--heightmap FILE] [--heightmap-cell SIZE] [--heightmap-height HEIGHT] \
+        [--frame-add] [--frame-update] [--lock-camera]";
+
+    fn parse() -> Self {
+        let mut script = None;
+        let mut timeline = None;
+        let mut timeline_loop = false;
+        let mut sdf = None;
+        let mut heightmap = None;
+        let mut heightmap_cell = 1.0;
+        let mut heightmap_height = 10.0;
+        let mut frame_add = false;
+        let mut frame_update = false;
+        let mut lock_camera = false;
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--sdf" => sdf = Some(args.next().expect("--sdf needs a path").into()),
+                "--heightmap" => {
+                    heightmap = Some(args.next().expect("--heightmap needs a path").into())
+                }
+                "--heightmap-cell" => {
+                    heightmap_cell = args
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .expect("--heightmap-cell needs a number")
+                }
+                "--heightmap-height" => {
+                    heightmap_height = args
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .expect("--heightmap-height needs a number")
+                }
+                "--timeline" => {
+                    timeline = Some(args.next().expect("--timeline needs a path").into())
+                }
+                "--timeline-loop" => timeline_loop = true,
+                "--frame-add" => frame_add = true,
+                "--frame-update" => frame_update = true,
+                "--lock-camera" => lock_camera = true,
+                _ if arg.starts_with("--") => {
+                    panic!("unknown argument {arg}\n{}", Self::USAGE)
+                }
+                _ if script.is_some() => {
+                    panic!("more than one script given: {arg}\n{}", Self::USAGE)
+                }
+                _ => script = Some(arg.into()),
+            }
+        }
+
+        Self {
+            script,
+            timeline,
+            timeline_loop,
+            sdf,
+            heightmap,
+            heightmap_cell,
+            heightmap_height,
+            frame_add,
+            frame_update,
+            lock_camera,
+        }
+    }
+}
 
 fn main() {
-    let mut args = std::env::args();
-    let path = args.nth(1).expect("need a path to a script!");
+    let args = Args::parse();
+
+    if let Some(path) = &args.sdf {
+        let sdf = Sdf::load(path)
+            .unwrap_or_else(|e| panic!("could not load SDF from {}: {e}", path.display()));
+        let _ = SDF.set(sdf);
+    }
+
+    if let Some(path) = &args.heightmap {
+        let heightmap = Heightmap::load(path, args.heightmap_cell, args.heightmap_height)
+            .unwrap_or_else(|e| panic!("could not load heightmap from {}: {e}", path.display()));
+        let _ = HEIGHTMAP.set(heightmap);
+    }
 
-    App::new()
-        .insert_resource(ScriptManager::new(Path::new(&path)))
+    let timeline = args.timeline.as_ref().map(|path| {
+        Timeline::load(path, args.timeline_loop)
+            .unwrap_or_else(|e| panic!("could not load timeline from {}: {e}", path.display()))
+    });
+    let script = match (&timeline, &args.script) {
+        (Some(timeline), _) => timeline.first_script().to_path_buf(),
+        (None, Some(script)) => script.clone(),
+        (None, None) => panic!("need a path to a script or a timeline!"),
+    };
+
+    let mut app = App::new();
+    if let Some(timeline) = timeline {
+        app.insert_resource(timeline);
+    }
+    app.insert_resource(ScriptManager::new(&script))
+        .init_resource::<MouseForce>()
+        .init_resource::<ParticleLabels>()
+        .insert_resource(CameraBookmarks::load())
+        .insert_resource(CameraLock {
+            locked: args.lock_camera,
+        })
+        .init_resource::<RngFreeze>()
         .add_plugins((
             DefaultPlugins,
             CustomMaterialPlugin,
@@ -29,22 +177,194 @@ fn main() {
         ))
         .add_systems(Startup, setup)
         .add_systems(Startup, time_in_roto_setup)
+        .add_systems(Startup, bookmark_message_setup)
+        .add_systems(Startup, particle_labels_setup)
         .add_systems(
             FixedUpdate,
             (
+                play_timeline.before(reload_script),
                 reload_script,
-                add_particles,
-                update_particles,
-                update_instances,
                 time_in_roto_update,
             ),
         )
-        .add_systems(Update, orbit)
-        .run();
+        .add_systems(
+            Update,
+            (
+                orbit,
+                toggle_camera_lock,
+                toggle_rng_freeze,
+                toggle_render_mode,
+                update_window_info,
+                update_input_info,
+                update_gamepad_info,
+                update_time_info,
+                update_camera_info.after(orbit),
+                measure_particles,
+                toggle_mouse_force,
+                apply_mouse_force,
+                camera_bookmarks.after(orbit),
+                toggle_particle_labels,
+                update_particle_labels,
+                update_shading,
+                print_docs,
+            ),
+        );
+
+    // By default the script runs at the fixed rate, so every call advances the
+    // simulation by the same amount of time and runs are reproducible. Running
+    // it every frame instead gives smoother motion, but then the time between
+    // calls varies with the frame rate, so scripts that move particles by a
+    // fixed amount per call will run faster on faster machines. Scripts can
+    // scale their motion by `delta_time` to compensate.
+    if args.frame_add {
+        app.add_systems(Update, (reseed_rng, add_particles).chain());
+    } else {
+        app.add_systems(FixedUpdate, (reseed_rng, add_particles).chain());
+    }
+    if args.frame_update {
+        app.add_systems(Update, (update_particles, update_instances));
+    } else {
+        app.add_systems(FixedUpdate, (update_particles, update_instances));
+    }
+
+    app.run();
 }
 
+/// Particles emitted by the script that haven't been added yet.
+///
+/// This is a queue: particles are pushed in the order they are emitted and
+/// `add_particles` drains it from the front, so the particles end up in
+/// [`Particles`] in emit order. Replaying the same script therefore gives
+/// the same particle order every step.
 static EMITTER: Mutex<Vec<Particle>> = Mutex::new(Vec::new());
 
+/// Particles emitted by the script that are held back for a while.
+///
+/// `add_particles` counts down their delays and moves them to [`EMITTER`]
+/// once they are due, in the order they were queued.
+static DELAYED: Mutex<Vec<DelayedParticle>> = Mutex::new(Vec::new());
+
+struct DelayedParticle {
+    /// Seconds left before the particle is emitted.
+    delay: f32,
+    particle: Particle,
+}
+
+/// Engine settings that scripts can change.
+///
+/// These are reset to their defaults whenever the script is reloaded, so
+/// removing a call from the script also removes its effect.
+static SETTINGS: Mutex<Settings> = Mutex::new(Settings::DEFAULT);
+
+struct Settings {
+    /// Only call `add` during the first `on_time` seconds of every `period`.
+    emit_duty: Option<EmitDuty>,
+    /// Number of times per second to call `spawn_one`.
+    emit_rate: f32,
+    /// Remove the oldest particles when there are more than this many.
+    soft_cap: Option<usize>,
+    /// Largest length of the velocity of a particle after `update`.
+    max_speed: Option<f32>,
+    /// Fog applied to the particles, see [`ParticleShading`].
+    fog_density: f32,
+    fog_color: Color,
+    /// Soft edge of points, see [`ParticleShading`].
+    edge_softness: f32,
+    /// Factor between the scale of a particle and the size it is drawn at.
+    render_scale: f32,
+    /// Seconds before the end of their lifetime over which particles fade
+    /// out.
+    fade_out: f32,
+}
+
+impl Settings {
+    const DEFAULT: Self = Self {
+        emit_duty: None,
+        emit_rate: 0.0,
+        soft_cap: None,
+        max_speed: None,
+        fog_density: 0.0,
+        fog_color: CLEAR_COLOR,
+        edge_softness: 0.0,
+        render_scale: 1.0,
+        fade_out: 0.0,
+    };
+}
+
+#[derive(Clone, Copy)]
+struct EmitDuty {
+    on_time: f32,
+    period: f32,
+}
+
+impl EmitDuty {
+    fn is_on(&self, time: f32) -> bool {
+        time.rem_euclid(self.period) < self.on_time
+    }
+}
+
+/// Information about the engine that scripts can read.
+///
+/// This is written by the engine systems, so it can lag a frame behind.
+static ENGINE_INFO: Mutex<EngineInfo> = Mutex::new(EngineInfo::DEFAULT);
+
+struct EngineInfo {
+    /// Number of instances drawn in the last frame.
+    instance_count: usize,
+    /// Whether the primary window has focus.
+    window_focused: bool,
+    /// Largest distance of any particle from the centroid of all particles.
+    bounding_radius: f32,
+    /// Time the oldest particle has been alive for.
+    oldest_age: f32,
+    /// Sum of the kinetic energies of all particles.
+    total_energy: f32,
+    /// Average velocity of all particles.
+    mean_velocity: Vec3,
+    /// View-projection matrix of the camera.
+    clip_from_world: Mat4,
+    /// Position of the camera in the world.
+    camera_position: Vec3,
+    /// Direction held with WASD for X and Z and with Q and E for Y.
+    move_axis: Vec3,
+    /// Positions of all particles, sorted for lookups near a point.
+    spatial_hash: SpatialHash,
+    /// Speed of the simulation relative to real time.
+    time_scale: f32,
+    /// Seconds between the current and the previous call to `update`.
+    update_delta: f32,
+    /// How far the last frame was between two fixed steps, from 0 to 1.
+    interpolation_alpha: f32,
+    /// Sticks and analog triggers of the first gamepad, in the order of
+    /// the script library's `gamepad_axis`.
+    gamepad_axes: [f32; 6],
+    /// Buttons of the first gamepad, in the order of [`GamepadButton::all`].
+    gamepad_buttons: [bool; 19],
+}
+
+impl EngineInfo {
+    const DEFAULT: Self = Self {
+        instance_count: 0,
+        window_focused: true,
+        bounding_radius: 0.0,
+        oldest_age: 0.0,
+        total_energy: 0.0,
+        mean_velocity: Vec3::ZERO,
+        clip_from_world: Mat4::IDENTITY,
+        camera_position: Vec3::ZERO,
+        move_axis: Vec3::ZERO,
+        spatial_hash: SpatialHash::EMPTY,
+        time_scale: 1.0,
+        update_delta: 0.0,
+        interpolation_alpha: 0.0,
+        gamepad_axes: [0.0; 6],
+        gamepad_buttons: [false; 19],
+    };
+}
+
+/// Id handed to the next emitted particle.
+static NEXT_PARTICLE_ID: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Component)]
 struct Particles(Vec<ParticleWithTime>);
 
@@ -55,14 +375,69 @@ struct ParticleWithTime {
 
 #[derive(Clone, Debug)]
 struct Particle {
+    /// Assigned by the engine at emit time and preserved across updates.
+    id: u64,
     pos: Vec3,
+    /// Velocity in units per call to `update`. The engine doesn't move
+    /// particles, so scripts integrate it themselves in `update`.
+    vel: Vec3,
     scale: f32,
     color: Color,
+    /// Script-defined kind of particle, ignored by the engine.
+    kind: i32,
+    /// Always positive, see [`Particle::MIN_MASS`].
+    mass: f32,
+    /// Age in seconds after which the engine removes the particle.
+    lifetime: f32,
+    /// Orientation of the instanced mesh. Points always face the camera.
+    rotation: Quat,
+}
+
+impl Particle {
+    /// Smallest mass a particle can have, so forces can always be divided
+    /// by the mass.
+    const MIN_MASS: f32 = 1e-6;
+
+    /// A particle with the default kind, mass, lifetime and rotation.
+    ///
+    /// The id is assigned when the particle is emitted.
+    fn new(pos: Vec3, vel: Vec3, scale: f32, color: Color) -> Self {
+        Self {
+            id: 0,
+            pos,
+            vel,
+            scale,
+            color,
+            kind: 0,
+            mass: 1.0,
+            lifetime: f32::INFINITY,
+            rotation: Quat::IDENTITY,
+        }
+    }
+
+    fn next_id() -> u64 {
+        NEXT_PARTICLE_ID.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Background color of the scene and the default fog color.
+const CLEAR_COLOR: Color = Color::srgb(0.0, 0.0, 0.05);
+
+/// The meshes used to draw particles in each [`ParticleRenderMode`].
+#[derive(Resource)]
+struct ParticleMeshes {
+    sphere: Handle<Mesh>,
+    point: Handle<Mesh>,
 }
 
 fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    let particle_meshes = ParticleMeshes {
+        sphere: meshes.add(Sphere::new(0.5)),
+        point: meshes.add(Rectangle::new(1.0, 1.0)),
+    };
+
     commands.spawn((
-        Mesh3d(meshes.add(Sphere::new(0.5))),
+        Mesh3d(particle_meshes.sphere.clone()),
         InstanceMaterialData(Vec::new()),
         NoFrustumCulling,
     ));
@@ -71,13 +446,83 @@ fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
 
     commands.spawn((
         Camera {
-            clear_color: ClearColorConfig::Custom(Color::from(Srgba::rgb(0.0, 0.0, 0.05))),
+            clear_color: ClearColorConfig::Custom(CLEAR_COLOR),
             ..default()
         },
         Camera3d::default(),
         Transform::from_xyz(0.0, 0.0, 30.0).looking_at(Vec3::ZERO, Vec3::Y),
         NoIndirectDrawing,
     ));
+
+    commands.insert_resource(particle_meshes);
+}
+
+/// Switch between drawing particles as spheres and as glowing points.
+fn toggle_render_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut render_mode: ResMut<ParticleRenderMode>,
+    particle_meshes: Res<ParticleMeshes>,
+    mut mesh: Single<&mut Mesh3d, With<InstanceMaterialData>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+
+    let (mode, handle) = match *render_mode {
+        ParticleRenderMode::Spheres => (ParticleRenderMode::Points, &particle_meshes.point),
+        ParticleRenderMode::Points => (ParticleRenderMode::Spheres, &particle_meshes.sphere),
+    };
+    *render_mode = mode;
+    mesh.0 = handle.clone();
+}
+
+fn update_shading(mut shading: ResMut<ParticleShading>) {
+    let settings = SETTINGS.lock().unwrap();
+    shading.set_if_neq(ParticleShading {
+        fog_color: LinearRgba::from(settings.fog_color).to_vec4(),
+        fog_density: settings.fog_density,
+        edge_softness: settings.edge_softness,
+    });
+}
+
+/// Whether the random functions of the script library repeat the same
+/// values every step.
+///
+/// Freezing the randomness while time keeps going makes it possible to tell
+/// apart what a script does randomly from what it does over time. Toggled
+/// with `R`.
+#[derive(Resource, Default)]
+struct RngFreeze {
+    frozen: bool,
+}
+
+/// Seed the random number generator is reset to while it is frozen.
+const FROZEN_SEED: u64 = 0;
+
+fn toggle_rng_freeze(keys: Res<ButtonInput<KeyCode>>, mut freeze: ResMut<RngFreeze>) {
+    if keys.just_pressed(KeyCode::KeyR) {
+        freeze.frozen = !freeze.frozen;
+        if !freeze.frozen {
+            *RNG.lock().unwrap() = StdRng::from_os_rng();
+        }
+    }
+}
+
+/// Reset the random number generator before every step while it is frozen,
+/// so every call to `add` and `update` sees the same random values.
+fn reseed_rng(freeze: Res<RngFreeze>) {
+    if freeze.frozen {
+        *RNG.lock().unwrap() = StdRng::seed_from_u64(FROZEN_SEED);
+    }
+}
+
+/// Print the functions and types that scripts can use when `H` is pressed.
+fn print_docs(keys: Res<ButtonInput<KeyCode>>, manager: Res<ScriptManager>) {
+    if keys.just_pressed(KeyCode::KeyH)
+        && let Err(e) = manager.print_docs()
+    {
+        eprintln!("Could not print the documentation: {e}");
+    }
 }
 
 fn reload_script(mut manager: ResMut<ScriptManager>) {
@@ -89,7 +534,12 @@ fn add_particles(
     mut manager: ResMut<ScriptManager>,
     mut particles: Single<&mut Particles>,
 ) {
-    if let Some(add) = &manager.add {
+    STEP_EMIT_COUNT.store(0, Ordering::Relaxed);
+
+    let emit_duty = SETTINGS.lock().unwrap().emit_duty;
+    let emitting = emit_duty.is_none_or(|duty| duty.is_on(time.elapsed_secs()));
+
+    if emitting && let Some(add) = &manager.add {
         let t1 = Instant::now();
         add.call(&mut (), time.elapsed_secs());
         let t2 = Instant::now();
@@ -101,22 +551,70 @@ fn add_particles(
         // let y = rng.random_range(-10.0..10.0);
 
         // let particle = Particle {
+        //     id: Particle::next_id(),
         //     pos: Vec3 { x, y, z: 0. },
+        //     vel: Vec3::ZERO,
         //     scale: 1.0,
         //     color: Color::from(Srgba::RED),
+        //     kind: 0,
+        //     mass: 1.0,
+        //     lifetime: f32::INFINITY,
+        //     rotation: Quat::IDENTITY,
         // };
         // EMITTER.lock().unwrap().push(particle);
     }
 
+    // Keep the fractional part of the particles that are due, so the rate
+    // doesn't depend on how often this system runs.
+    let emit_rate = SETTINGS.lock().unwrap().emit_rate;
+    manager.emit_accumulator += emit_rate * time.delta_secs();
+    let count = manager.emit_accumulator.floor();
+    manager.emit_accumulator -= count;
+
+    if let Some(spawn_one) = &manager.spawn_one {
+        let spawned: Vec<_> = (0..count as u32)
+            .map(|_| {
+                let Val(mut particle) = spawn_one.call(&mut (), time.elapsed_secs());
+                particle.id = Particle::next_id();
+                particle
+            })
+            .collect();
+        EMITTER.lock().unwrap().extend(spawned);
+    }
+
     let mut e = EMITTER.lock().unwrap();
-    for particle in e.drain(..) {
-        particles.0.push(ParticleWithTime {
-            start_time: time.elapsed_secs(),
-            particle: particle.clone(),
-        });
+    DELAYED.lock().unwrap().retain_mut(|delayed| {
+        delayed.delay -= time.delta_secs();
+        if delayed.delay > 0.0 {
+            return true;
+        }
+        let mut particle = delayed.particle.clone();
+        particle.id = Particle::next_id();
+        e.push(particle);
+        false
+    });
+
+    drain_emitted(&mut e, &mut particles.0, time.elapsed_secs());
+
+    // New particles are always appended and removing particles keeps the
+    // order, so the oldest particles are at the front.
+    if let Some(cap) = SETTINGS.lock().unwrap().soft_cap {
+        let excess = particles.0.len().saturating_sub(cap);
+        particles.0.drain(..excess);
     }
 }
 
+/// Move the particles in `queue` to the end of `into`, starting them at
+/// time `t`.
+///
+/// The particles are moved in insertion order, see [`EMITTER`].
+fn drain_emitted(queue: &mut Vec<Particle>, into: &mut Vec<ParticleWithTime>, t: f32) {
+    into.extend(queue.drain(..).map(|particle| ParticleWithTime {
+        start_time: t,
+        particle,
+    }));
+}
+
 fn update_particles(
     mut manager: ResMut<ScriptManager>,
     time: Res<Time>,
@@ -126,17 +624,28 @@ fn update_particles(
         return;
     };
 
+    ENGINE_INFO.lock().unwrap().update_delta = time.delta_secs();
+    let max_speed = SETTINGS.lock().unwrap().max_speed;
+
     let t1 = Instant::now();
     particles.0.retain_mut(|p| {
         let t = time.elapsed_secs() - p.start_time;
         let particle = p.particle.clone();
         let res = update.call(&mut (), t, Val(particle));
 
-        if let Some(Val(new)) = res {
-            p.particle = new;
-            true
-        } else {
-            false
+        // The script can remove a particle at any time by returning `None`,
+        // and particles it keeps are removed once they outlive the lifetime
+        // of the particle it returned.
+        match res {
+            Some(Val(mut new)) if t <= new.lifetime => {
+                new.id = p.particle.id;
+                if let Some(max_speed) = max_speed {
+                    new.vel = new.vel.clamp_length_max(max_speed);
+                }
+                p.particle = new;
+                true
+            }
+            _ => false,
         }
     });
     let t2 = Instant::now();
@@ -145,6 +654,7 @@ fn update_particles(
 }
 
 fn update_instances(
+    time: Res<Time>,
     particles: Single<&Particles>,
     mut instances: Single<&mut InstanceMaterialData>,
 ) {
@@ -152,11 +662,126 @@ fn update_instances(
         .0
         .resize(particles.0.len(), InstanceData::default());
 
+    let (render_scale, fade_out) = {
+        let settings = SETTINGS.lock().unwrap();
+        (settings.render_scale, settings.fade_out)
+    };
     for (p, i) in particles.0.iter().zip(&mut instances.0) {
+        // The fade is applied when drawing rather than to the particle, so it
+        // doesn't compound with the alpha of the previous step.
+        let mut color = LinearRgba::from(p.particle.color);
+        if fade_out > 0.0 {
+            let remaining = p.particle.lifetime - (time.elapsed_secs() - p.start_time);
+            color.alpha *= (remaining / fade_out).clamp(0.0, 1.0);
+        }
+
         i.position = p.particle.pos;
-        i.scale = p.particle.scale;
-        i.color = LinearRgba::from(p.particle.color).to_f32_array();
+        i.scale = p.particle.scale * render_scale;
+        i.color = color.to_f32_array();
+        i.rotation = p.particle.rotation.to_array();
     }
+
+    ENGINE_INFO.lock().unwrap().instance_count = instances.0.len();
+}
+
+/// Compute the aggregate particle statistics that scripts can read.
+fn measure_particles(time: Res<Time>, particles: Single<&Particles>) {
+    let count = particles.0.len();
+    let centroid = if count == 0 {
+        Vec3::ZERO
+    } else {
+        particles.0.iter().map(|p| p.particle.pos).sum::<Vec3>() / count as f32
+    };
+    let mean_velocity = if count == 0 {
+        Vec3::ZERO
+    } else {
+        particles.0.iter().map(|p| p.particle.vel).sum::<Vec3>() / count as f32
+    };
+
+    let bounding_radius = particles
+        .0
+        .iter()
+        .map(|p| p.particle.pos.distance(centroid))
+        .fold(0.0, f32::max);
+
+    let oldest_age = particles
+        .0
+        .iter()
+        .map(|p| time.elapsed_secs() - p.start_time)
+        .fold(0.0, f32::max);
+
+    let total_energy = particles
+        .0
+        .iter()
+        .map(|p| 0.5 * p.particle.mass * p.particle.vel.length_squared())
+        .sum();
+
+    let mut info = ENGINE_INFO.lock().unwrap();
+    info.bounding_radius = bounding_radius;
+    info.oldest_age = oldest_age;
+    info.total_energy = total_energy;
+    info.mean_velocity = mean_velocity;
+    info.spatial_hash
+        .rebuild(particles.0.iter().map(|p| p.particle.pos));
+}
+
+fn update_window_info(window: Single<&Window, With<PrimaryWindow>>) {
+    ENGINE_INFO.lock().unwrap().window_focused = window.focused;
+}
+
+fn update_input_info(keys: Res<ButtonInput<KeyCode>>) {
+    let axis = |negative, positive| {
+        let mut value = 0.0;
+        if keys.pressed(negative) {
+            value -= 1.0;
+        }
+        if keys.pressed(positive) {
+            value += 1.0;
+        }
+        value
+    };
+
+    ENGINE_INFO.lock().unwrap().move_axis = Vec3::new(
+        axis(KeyCode::KeyA, KeyCode::KeyD),
+        axis(KeyCode::KeyQ, KeyCode::KeyE),
+        axis(KeyCode::KeyW, KeyCode::KeyS),
+    );
+}
+
+fn update_gamepad_info(gamepads: Query<&Gamepad>) {
+    let mut info = ENGINE_INFO.lock().unwrap();
+    let Some(gamepad) = gamepads.iter().next() else {
+        info.gamepad_axes = [0.0; 6];
+        info.gamepad_buttons = [false; 19];
+        return;
+    };
+
+    // Bevy reports the analog triggers of a gamepad as buttons, while
+    // `GamepadAxis::LeftZ` and `RightZ` are the throttle and yaw of a HOTAS.
+    info.gamepad_axes = [
+        gamepad.get(GamepadAxis::LeftStickX),
+        gamepad.get(GamepadAxis::LeftStickY),
+        gamepad.get(GamepadButton::LeftTrigger2),
+        gamepad.get(GamepadAxis::RightStickX),
+        gamepad.get(GamepadAxis::RightStickY),
+        gamepad.get(GamepadButton::RightTrigger2),
+    ]
+    .map(|value| value.unwrap_or(0.0));
+    info.gamepad_buttons = GamepadButton::all().map(|button| gamepad.pressed(button));
+}
+
+fn update_time_info(time: Res<Time<Virtual>>, fixed_time: Res<Time<Fixed>>) {
+    let mut info = ENGINE_INFO.lock().unwrap();
+    info.time_scale = time.relative_speed();
+    info.interpolation_alpha = fixed_time.overstep_fraction();
+}
+
+fn update_camera_info(camera: Single<(&Camera, &GlobalTransform)>) {
+    let (camera, camera_transform) = *camera;
+    let view_from_world = camera_transform.to_matrix().inverse();
+    let mut info = ENGINE_INFO.lock().unwrap();
+    info.clip_from_world = camera.clip_from_view() * view_from_world;
+    info.camera_position = camera_transform.translation();
 }
 
 #[derive(Component, Clone, Copy)]
@@ -164,6 +789,10 @@ enum TimeInRotoText {
     Add,
     Update,
     Particles,
+    Reloads,
+    Memory,
+    Camera,
+    Random,
 }
 
 fn time_in_roto_setup(mut commands: Commands) {
@@ -205,6 +834,35 @@ fn time_in_roto_setup(mut commands: Commands) {
                 Pickable::IGNORE,
             ))
             .with_child(TextSpan::default());
+            p.spawn((
+                Text::new("Reloads: "),
+                TextColor(Color::from(Srgba::WHITE)),
+                TimeInRotoText::Reloads,
+                Pickable::IGNORE,
+            ))
+            .with_child(TextSpan::default());
+            p.spawn((
+                Text::new("Memory: "),
+                TextColor(Color::from(Srgba::WHITE)),
+                TimeInRotoText::Memory,
+                Pickable::IGNORE,
+            ))
+            .with_child(TextSpan::default())
+            .with_child(TextSpan::new("KiB"));
+            p.spawn((
+                Text::new("Camera: "),
+                TextColor(Color::from(Srgba::WHITE)),
+                TimeInRotoText::Camera,
+                Pickable::IGNORE,
+            ))
+            .with_child(TextSpan::default());
+            p.spawn((
+                Text::new("Random: "),
+                TextColor(Color::from(Srgba::WHITE)),
+                TimeInRotoText::Random,
+                Pickable::IGNORE,
+            ))
+            .with_child(TextSpan::default());
         });
 }
 
@@ -213,6 +871,9 @@ fn time_in_roto_update(
     mut writer: TextUiWriter,
     manager: Res<ScriptManager>,
     particles: Single<&Particles>,
+    instances: Single<&InstanceMaterialData>,
+    camera_lock: Res<CameraLock>,
+    rng_freeze: Res<RngFreeze>,
 ) {
     for (entity, time_in_roto) in &query {
         match time_in_roto {
@@ -225,10 +886,57 @@ fn time_in_roto_update(
             TimeInRotoText::Particles => {
                 *writer.text(entity, 1) = format!("{:>8}", particles.0.len());
             }
+            TimeInRotoText::Reloads => {
+                *writer.text(entity, 1) = format!("{:>8}", manager.reload_count);
+            }
+            TimeInRotoText::Memory => {
+                let bytes = particle_memory(&particles, &instances);
+                *writer.text(entity, 1) = format!("{:>8}", bytes / 1024);
+            }
+            TimeInRotoText::Camera => {
+                let state = if camera_lock.locked { "locked" } else { "free" };
+                *writer.text(entity, 1) = format!("{state:>8}");
+            }
+            TimeInRotoText::Random => {
+                let state = if rng_freeze.frozen { "frozen" } else { "live" };
+                *writer.text(entity, 1) = format!("{state:>8}");
+            }
         }
     }
 }
 
+/// Approximate number of bytes allocated for the particle buffers.
+///
+/// This counts the capacity of the buffers rather than their length, so it
+/// shows how much memory they hold on to.
+fn particle_memory(particles: &Particles, instances: &InstanceMaterialData) -> usize {
+    let emitted = EMITTER.lock().unwrap().capacity() * size_of::<Particle>();
+    let delayed = DELAYED.lock().unwrap().capacity() * size_of::<DelayedParticle>();
+    let spatial_hash =
+        ENGINE_INFO.lock().unwrap().spatial_hash.capacity() * size_of::<([i32; 3], Vec3)>();
+    particles.0.capacity() * size_of::<ParticleWithTime>()
+        + instances.0.capacity() * size_of::<InstanceData>()
+        + emitted
+        + delayed
+        + spatial_hash
+}
+
+/// Whether the orbit camera ignores mouse input.
+///
+/// Locking the camera keeps the framing fixed, for example while recording.
+/// Toggled with `C` and enabled at startup with `--lock-camera`. Camera
+/// bookmarks still work while the camera is locked.
+#[derive(Resource)]
+struct CameraLock {
+    locked: bool,
+}
+
+fn toggle_camera_lock(keys: Res<ButtonInput<KeyCode>>, mut lock: ResMut<CameraLock>) {
+    if keys.just_pressed(KeyCode::KeyC) {
+        lock.locked = !lock.locked;
+    }
+}
+
 #[derive(Debug, Resource)]
 struct CameraSettings {
     pub pitch_speed: f32,
@@ -244,7 +952,15 @@ fn orbit(
     mouse_motion: Res<AccumulatedMouseMotion>,
     mut mouse_wheel_reader: MessageReader<MouseWheel>,
     time: Res<Time>,
+    lock: Res<CameraLock>,
 ) {
+    if lock.locked {
+        // Skip the scroll events received while locked, so they don't zoom
+        // the camera right after it is unlocked.
+        mouse_wheel_reader.clear();
+        return;
+    }
+
     let pitch_limit = std::f32::consts::FRAC_PI_2 - 0.01;
     let camera_settings = CameraSettings {
         pitch_speed: 0.003,
@@ -291,3 +1007,33 @@ fn orbit(
     }
     camera.translation = target - camera.forward() * distance;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(id: u64) -> Particle {
+        let mut particle = Particle::new(Vec3::splat(id as f32), Vec3::ZERO, 1.0, Color::WHITE);
+        particle.id = id;
+        particle
+    }
+
+    #[test]
+    fn drain_keeps_emit_order() {
+        let mut queue: Vec<_> = [3, 0, 2, 1].map(particle).into();
+        let mut particles = vec![ParticleWithTime {
+            start_time: 0.0,
+            particle: particle(7),
+        }];
+
+        drain_emitted(&mut queue, &mut particles, 1.0);
+
+        assert!(queue.is_empty());
+        let ids: Vec<_> = particles.iter().map(|p| p.particle.id).collect();
+        assert_eq!(ids, [7, 3, 0, 2, 1]);
+        let positions: Vec<_> = particles.iter().map(|p| p.particle.pos.x).collect();
+        assert_eq!(positions, [7.0, 3.0, 0.0, 2.0, 1.0]);
+        assert_eq!(particles[0].start_time, 0.0);
+        assert!(particles[1..].iter().all(|p| p.start_time == 1.0));
+    }
+}
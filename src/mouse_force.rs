@@ -0,0 +1,72 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::Particles;
+
+/// Distance from the cursor within which particles are pushed.
+const RADIUS: f32 = 5.0;
+
+/// How far a particle right at the cursor is moved per second.
+const STRENGTH: f32 = 20.0;
+
+/// Whether dragging with the right mouse button pushes particles around.
+///
+/// Toggled with `F`. While enabled, right-dragging pushes particles away from
+/// the cursor, or pulls them in while shift is held. The cursor is placed on
+/// the plane through the origin facing the camera.
+#[derive(Resource, Default)]
+pub struct MouseForce {
+    pub enabled: bool,
+}
+
+pub fn toggle_mouse_force(keys: Res<ButtonInput<KeyCode>>, mut force: ResMut<MouseForce>) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        force.enabled = !force.enabled;
+    }
+}
+
+pub fn apply_mouse_force(
+    force: Res<MouseForce>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    time: Res<Time>,
+    mut particles: Single<&mut Particles>,
+) {
+    if !force.enabled || !mouse_buttons.pressed(MouseButton::Right) {
+        return;
+    }
+
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    let (camera, camera_transform) = *camera;
+    let Ok(ray) = camera.viewport_to_world(camera_transform, cursor) else {
+        return;
+    };
+    let plane = InfinitePlane3d::new(camera_transform.forward());
+    let Some(distance) = ray.intersect_plane(Vec3::ZERO, plane) else {
+        return;
+    };
+    let center = ray.get_point(distance);
+
+    let attract = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let sign = if attract { -1.0 } else { 1.0 };
+
+    for p in &mut particles.0 {
+        let offset = p.particle.pos - center;
+        let distance = offset.length();
+        if distance >= RADIUS {
+            continue;
+        }
+
+        // Fall off linearly towards the edge of the radius.
+        let falloff = 1.0 - distance / RADIUS;
+        let step = sign * STRENGTH * falloff * time.delta_secs();
+
+        // Don't pull particles past the cursor.
+        let step = step.max(-distance);
+        p.particle.pos += offset.normalize_or_zero() * step;
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::math::{FloatExt, IVec3, Vec3};
+
+/// Most octaves [`turbulence`] sums, to bound the cost per call.
+pub const MAX_OCTAVES: u32 = 6;
+
+/// Step used to estimate the derivatives of the noise for the curl.
+const EPSILON: f32 = 1e-2;
+
+/// How fast the noise field scrolls through space per second, so the
+/// turbulence changes over time.
+const DRIFT: Vec3 = Vec3::new(0.37, 0.61, 0.29);
+
+/// Offsets that make the three components of the potential independent.
+const OFFSETS: [Vec3; 3] = [
+    Vec3::ZERO,
+    Vec3::new(31.4, 47.9, 12.3),
+    Vec3::new(-19.2, 8.7, 63.1),
+];
+
+/// Divergence-free velocity perturbation at `p` and time `t`.
+///
+/// Sums the curl of a value noise field over `octaves` octaves. Every octave
+/// doubles the frequency and halves the amplitude of the previous one, with
+/// the first octave at a frequency of 1 per unit and an amplitude of 1. The
+/// number of octaves is capped at [`MAX_OCTAVES`].
+pub fn turbulence(p: Vec3, t: f32, octaves: u32) -> Vec3 {
+    let p = p + DRIFT * t;
+    let mut sum = Vec3::ZERO;
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    for _ in 0..octaves.min(MAX_OCTAVES) {
+        sum += amplitude * curl(p * frequency);
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    sum
+}
+
+/// Curl of the vector potential made of three value noise fields, using
+/// central differences.
+fn curl(p: Vec3) -> Vec3 {
+    let potential = |p: Vec3| {
+        Vec3::new(
+            value_noise(p + OFFSETS[0]),
+            value_noise(p + OFFSETS[1]),
+            value_noise(p + OFFSETS[2]),
+        )
+    };
+    let derivative = |axis: Vec3| {
+        (potential(p + axis * EPSILON) - potential(p - axis * EPSILON)) / (2.0 * EPSILON)
+    };
+    let dx = derivative(Vec3::X);
+    let dy = derivative(Vec3::Y);
+    let dz = derivative(Vec3::Z);
+    Vec3::new(dy.z - dz.y, dz.x - dx.z, dx.y - dy.x)
+}
+
+/// Smoothly interpolated random values between -1 and 1 on the integer
+/// lattice.
+fn value_noise(p: Vec3) -> f32 {
+    let i = p.floor().as_ivec3();
+    let f = p - p.floor();
+    // Quintic fade, so the derivatives are continuous across cells too.
+    let f = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    let c = |dx, dy, dz| hash(i + IVec3::new(dx, dy, dz));
+    let x00 = c(0, 0, 0).lerp(c(1, 0, 0), f.x);
+    let x10 = c(0, 1, 0).lerp(c(1, 1, 0), f.x);
+    let x01 = c(0, 0, 1).lerp(c(1, 0, 1), f.x);
+    let x11 = c(0, 1, 1).lerp(c(1, 1, 1), f.x);
+    let y0 = x00.lerp(x10, f.y);
+    let y1 = x01.lerp(x11, f.y);
+    y0.lerp(y1, f.z)
+}
+
+/// Pseudo-random value between -1 and 1 for a lattice point.
+fn hash(i: IVec3) -> f32 {
+    let mut h = (i.x as u32).wrapping_mul(0x8da6_b343)
+        ^ (i.y as u32).wrapping_mul(0xd816_3841)
+        ^ (i.z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
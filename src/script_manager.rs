@@ -1,20 +1,108 @@
 use std::{
     path::{Path, PathBuf},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
     time::SystemTime,
 };
 
 use bevy::{
-    color::{Color, Mix, Srgba},
+    color::{Alpha, Color, LinearRgba, Mix, Srgba, palettes},
     ecs::resource::Resource,
-    math::Vec3,
+    math::{Mat4, Quat, Vec2, Vec3},
 };
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use roto::{Runtime, TypedFunc, Val, library};
 
-use crate::{EMITTER, Particle};
+use crate::{
+    DELAYED, DelayedParticle, EMITTER, ENGINE_INFO, EmitDuty, Particle, SETTINGS, Settings,
+    heightmap::HEIGHTMAP, noise, sdf::SDF,
+};
 
 type UpdateFn = fn(f32, Val<Particle>) -> Option<Val<Particle>>;
 type AddFn = fn(f32);
+type SpawnOneFn = fn(f32) -> Val<Particle>;
+
+/// Copy of [`ScriptManager::reload_count`] that the script library can read.
+static RELOAD_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// Number of fixed steps since the script was last compiled successfully.
+static STEPS_SINCE_RELOAD: AtomicU32 = AtomicU32::new(0);
+
+/// Number of particles emitted by the script since `add_particles` last
+/// started, which `emit_budgeted` checks against its budget.
+///
+/// `add_particles` resets it at the start of every step, whether or not it
+/// calls `add`.
+pub static STEP_EMIT_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// Random number generator behind the random functions of the script
+/// library.
+///
+/// It is shared so the engine can reseed it, see `RngFreeze`.
+pub static RNG: LazyLock<Mutex<StdRng>> = LazyLock::new(|| Mutex::new(StdRng::from_os_rng()));
+
+/// Random number generator that a script creates from a seed, for sequences
+/// that are the same on every run.
+///
+/// Clones share the generator, so every call to `next` advances it no matter
+/// which copy of the value it is called on.
+#[derive(Clone)]
+pub struct ScriptRng(Arc<Mutex<StdRng>>);
+
+/// Colors at positions along a line, with the colors in between blended.
+///
+/// The stops are kept sorted by position. Colors are blended in linear RGB,
+/// which keeps blends between bright colors from looking muddy.
+#[derive(Clone)]
+pub struct ColorGradient {
+    stops: Vec<(f32, LinearRgba)>,
+}
+
+impl ColorGradient {
+    fn sample(&self, t: f32) -> LinearRgba {
+        // Index of the first stop after `t`, so the segment containing `t`
+        // starts at the stop before it.
+        let i = self.stops.partition_point(|(position, _)| *position <= t);
+        let Some((from_t, from)) = i.checked_sub(1).map(|i| self.stops[i]) else {
+            return self.stops[0].1;
+        };
+        let Some(&(to_t, to)) = self.stops.get(i) else {
+            return from;
+        };
+        from.mix(&to, (t - from_t) / (to_t - from_t))
+    }
+}
+
+/// Queue a particle to be added to the simulation.
+fn queue(mut particle: Particle) {
+    // Assign the id while holding the lock, so ids always increase in the
+    // order the particles are queued.
+    let mut emitter = EMITTER.lock().unwrap();
+    particle.id = Particle::next_id();
+    emitter.push(particle);
+    STEP_EMIT_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Normally distributed random number with a mean of 0 and a standard
+/// deviation of 1, using the Box-Muller transform.
+fn standard_normal(rng: &mut StdRng) -> f32 {
+    // Keep `u` above 0 so its logarithm is finite.
+    let u: f32 = 1.0 - rng.random::<f32>();
+    let v: f32 = rng.random();
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+/// Normalized device coordinates of `p`, or `None` if it is behind the
+/// camera.
+///
+/// X goes from -1 on the left of the screen to 1 on the right, and Y from -1
+/// at the bottom to 1 at the top.
+fn to_ndc(clip_from_world: Mat4, p: Vec3) -> Option<Vec2> {
+    let clip = clip_from_world * p.extend(1.0);
+    (clip.w > 0.0).then(|| clip.truncate().truncate() / clip.w)
+}
 
 #[derive(Resource)]
 pub struct ScriptManager {
@@ -22,39 +110,731 @@ pub struct ScriptManager {
     pub path: PathBuf,
     pub last_compile: SystemTime,
     pub script_not_found_logged: bool,
+    /// Number of times the script was compiled successfully.
+    pub reload_count: u32,
     pub update: Option<TypedFunc<(), UpdateFn>>,
     pub update_ms: f32,
     pub add: Option<TypedFunc<(), AddFn>>,
     pub add_ms: f32,
+    pub spawn_one: Option<TypedFunc<(), SpawnOneFn>>,
+    /// Particles that are due to be spawned with `spawn_one`, including
+    /// the fraction of a particle left over from previous steps.
+    pub emit_accumulator: f32,
 }
 
 impl ScriptManager {
     pub fn new(path: &Path) -> Self {
         let lib = library! {
+            #[copy] type Vec2 = Val<Vec2>;
             #[copy] type Vec3 = Val<Vec3>;
+            #[copy] type Quat = Val<Quat>;
             #[copy] type Color = Val<Color>;
             #[clone] type Particle = Val<Particle>;
+            #[clone] type Rng = Val<ScriptRng>;
+            #[clone] type Gradient = Val<ColorGradient>;
 
             fn emit(particle: Val<Particle>) {
-                EMITTER.lock().unwrap().push(particle.0);
+                queue(particle.0);
+            }
+
+            /// Emit a particle only if fewer than `budget` particles were
+            /// emitted so far in this step.
+            ///
+            /// The count starts over right before `add` is called, or would
+            /// be called while emission is off, and includes the particles
+            /// emitted since then by `add` and by `update`. Particles emitted
+            /// with plain `emit` or `emit_fountain` count towards the budget
+            /// too, but are never limited by it. The budget only limits new
+            /// particles per step, independently of the soft cap, which still
+            /// removes the oldest particles once there are too many in total.
+            fn emit_budgeted(particle: Val<Particle>, budget: i32) {
+                if (STEP_EMIT_COUNT.load(Ordering::Relaxed) as i64) < budget as i64 {
+                    queue(particle.0);
+                }
+            }
+
+            /// Emit a particle from the origin with a velocity of `strength`
+            /// units per call to `update` in a random direction at most
+            /// `spread` radians away from straight up.
+            ///
+            /// Directions are spread evenly over the cone. The particle
+            /// only moves if `update` integrates its velocity, see
+            /// `Particle.vel`.
+            fn emit_fountain(strength: f32, spread: f32, color: Val<Color>) {
+                let dir = {
+                    let mut rng = RNG.lock().unwrap();
+                    let cos_max = spread.max(0.0).min(std::f32::consts::PI).cos();
+                    let cos = rng.random_range(cos_max..=1.0);
+                    let angle = rng.random_range(0.0..std::f32::consts::TAU);
+                    let sin = (1.0 - cos * cos).sqrt();
+                    Vec3::new(sin * angle.cos(), cos, sin * angle.sin())
+                };
+
+                queue(Particle::new(Vec3::ZERO, dir * strength, 0.3, color.0));
+            }
+
+            /// Emit `n` particles at `center` with velocities spread around
+            /// `base_vel`.
+            ///
+            /// Each component of the velocity gets normally distributed
+            /// noise with a standard deviation of `stddev` units per call to
+            /// `update`, which looks more natural than a uniform spread. A
+            /// negative `stddev` is treated as 0. The particles count
+            /// towards the soft cap like any other.
+            fn emit_gaussian_burst(
+                center: Val<Vec3>,
+                base_vel: Val<Vec3>,
+                stddev: f32,
+                n: i32,
+                color: Val<Color>,
+            ) {
+                let stddev = stddev.max(0.0);
+                for _ in 0..n.max(0) {
+                    let noise = {
+                        let mut rng = RNG.lock().unwrap();
+                        Vec3::new(
+                            standard_normal(&mut rng),
+                            standard_normal(&mut rng),
+                            standard_normal(&mut rng),
+                        )
+                    };
+                    queue(Particle::new(center.0, base_vel.0 + stddev * noise, 0.3, color.0));
+                }
+            }
+
+            /// Emit a particle at a random point on the circle of `radius`
+            /// around `center` perpendicular to `axis`, moving along the
+            /// circle at `speed` units per call to `update`.
+            ///
+            /// Seen with the axis pointing at the viewer, the particle moves
+            /// counterclockwise, following the right-hand rule. The axis
+            /// doesn't need to be normalized, and a zero axis is treated as
+            /// straight up. This only sets the initial velocity, so the
+            /// particle only orbits if `update` integrates its velocity and
+            /// pulls it towards the center.
+            fn emit_orbit(
+                center: Val<Vec3>,
+                radius: f32,
+                speed: f32,
+                axis: Val<Vec3>,
+                color: Val<Color>,
+            ) {
+                let axis = axis.try_normalize().unwrap_or(Vec3::Y);
+                let (u, v) = axis.any_orthonormal_pair();
+                let angle = RNG.lock().unwrap().random_range(0.0..std::f32::consts::TAU);
+                let offset = radius * (angle.cos() * u + angle.sin() * v);
+                let vel = axis.cross(offset).normalize_or_zero() * speed;
+                queue(Particle::new(center.0 + offset, vel, 0.3, color.0));
+            }
+
+            /// Emit `n` particles evenly spaced along the line from `a` to
+            /// `b`, appearing one after the other over `duration` seconds.
+            ///
+            /// The particle at `a` appears right away and the one at `b`
+            /// after `duration`, with the delays of the ones in between
+            /// proportional to their distance from `a`. A single particle is
+            /// emitted at `a` right away. The particles count towards the
+            /// soft cap once they appear.
+            fn emit_sweep(
+                a: Val<Vec3>,
+                b: Val<Vec3>,
+                n: i32,
+                duration: f32,
+                color: Val<Color>,
+            ) {
+                let mut delayed = DELAYED.lock().unwrap();
+                for i in 0..n.max(0) {
+                    let t = if n > 1 { i as f32 / (n - 1) as f32 } else { 0.0 };
+                    delayed.push(DelayedParticle {
+                        delay: t * duration.max(0.0),
+                        particle: Particle::new(a.lerp(b.0, t), Vec3::ZERO, 0.3, color.0),
+                    });
+                }
+            }
+
+            /// Triangle wave that goes from 0 up to `length` and back to 0.
+            ///
+            /// The period is `2 * length`. Returns 0 if `length` is not
+            /// positive.
+            fn ping_pong(t: f32, length: f32) -> f32 {
+                if length <= 0.0 {
+                    return 0.0;
+                }
+                length - (t.rem_euclid(2.0 * length) - length).abs()
+            }
+
+            /// Point at `t` on the cubic Bézier curve with control points
+            /// `p0` to `p3`.
+            ///
+            /// The curve runs from `p0` at `t = 0` to `p3` at `t = 1`. `t` is
+            /// not clamped, so values outside that range extrapolate the
+            /// curve.
+            fn bezier(
+                p0: Val<Vec3>,
+                p1: Val<Vec3>,
+                p2: Val<Vec3>,
+                p3: Val<Vec3>,
+                t: f32,
+            ) -> Val<Vec3> {
+                let u = 1.0 - t;
+                Val(
+                    u * u * u * p0.0
+                        + 3.0 * u * u * t * p1.0
+                        + 3.0 * u * t * t * p2.0
+                        + t * t * t * p3.0,
+                )
+            }
+
+            /// Uniformly random point on the surface of the axis-aligned box
+            /// between `min` and `max`.
+            ///
+            /// Faces are picked proportionally to their area, so every unit
+            /// of surface area is equally likely.
+            fn random_on_box(min: Val<Vec3>, max: Val<Vec3>) -> Val<Vec3> {
+                let (min, max) = (min.min(max.0), min.max(max.0));
+                let size = max - min;
+                let mut rng = RNG.lock().unwrap();
+                let mut p = min + size * Vec3::new(rng.random(), rng.random(), rng.random());
+
+                let areas = [size.y * size.z, size.x * size.z, size.x * size.y];
+                let total: f32 = areas.iter().sum();
+                if !(total > 0.0 && total.is_finite()) {
+                    // A flat or degenerate box is all surface. Boxes with
+                    // infinite or NaN corners have no proper surface, and
+                    // sampling their faces would panic.
+                    return Val(p);
+                }
+
+                // Pick the axis the face is perpendicular to, then one of
+                // the two faces along it.
+                let mut r = rng.random_range(0.0..total);
+                let mut axis = 2;
+                for (i, area) in areas.iter().enumerate() {
+                    if r < *area {
+                        axis = i;
+                        break;
+                    }
+                    r -= area;
+                }
+                p[axis] = if rng.random() { min[axis] } else { max[axis] };
+                Val(p)
+            }
+
+            /// Point at `t` on the Catmull-Rom spline segment from `p1` to
+            /// `p2`, with `p0` and `p3` as the neighbouring waypoints.
+            ///
+            /// This is the uniform variant with tension 0.5: the curve passes
+            /// through `p1` at `t = 0` and `p2` at `t = 1`, and its tangent
+            /// at each waypoint is parallel to the line between the
+            /// waypoints on either side. Chain segments by shifting the four
+            /// points along the path.
+            fn catmull_rom(
+                p0: Val<Vec3>,
+                p1: Val<Vec3>,
+                p2: Val<Vec3>,
+                p3: Val<Vec3>,
+                t: f32,
+            ) -> Val<Vec3> {
+                let t2 = t * t;
+                let t3 = t2 * t;
+                Val(
+                    0.5 * (2.0 * p1.0
+                        + (p2.0 - p0.0) * t
+                        + (2.0 * p0.0 - 5.0 * p1.0 + 4.0 * p2.0 - p3.0) * t2
+                        + (3.0 * p1.0 - p0.0 - 3.0 * p2.0 + p3.0) * t3),
+                )
+            }
+
+            /// Signed distance from `p` to the surface of the field loaded
+            /// with `--sdf`.
+            ///
+            /// Distances are negative inside solids. Points outside the grid,
+            /// or any point when no field was loaded, are reported as very
+            /// far away.
+            fn sdf(p: Val<Vec3>) -> f32 {
+                SDF.get().map_or(f32::MAX, |sdf| sdf.sample(p.0))
+            }
+
+            /// Swirling, smoke-like velocity perturbation at `p` and time `t`.
+            ///
+            /// Sums `octaves` octaves of curl noise, each with twice the
+            /// frequency and half the amplitude of the previous one. The
+            /// first octave varies over about one unit and has a magnitude
+            /// on the order of 1. At most 6 octaves are used, since each one
+            /// costs as much as the first. Add the result to a velocity.
+            fn turbulence(p: Val<Vec3>, t: f32, octaves: i32) -> Val<Vec3> {
+                Val(noise::turbulence(p.0, t, octaves.max(0) as u32))
+            }
+
+            /// Height of the terrain loaded with `--heightmap` at `x`, `z`.
+            ///
+            /// Heights are interpolated between pixels. Points beyond the
+            /// edges of the heightmap get the height of the nearest edge.
+            /// Without a heightmap, the terrain is flat at height 0.
+            fn height_at(x: f32, z: f32) -> f32 {
+                HEIGHTMAP.get().map_or(0.0, |heightmap| heightmap.sample(x, z))
+            }
+
+            /// Only call `add` during the first `on_time` seconds of every
+            /// `period` seconds.
+            ///
+            /// Emission is always on by default, and a `period` that is not
+            /// positive turns it back on. Since `add` is not called while
+            /// emission is off, the script can only change the duty cycle
+            /// during an on phase.
+            fn set_emit_duty(on_time: f32, period: f32) {
+                SETTINGS.lock().unwrap().emit_duty = if period > 0.0 {
+                    Some(EmitDuty { on_time, period })
+                } else {
+                    None
+                };
+            }
+
+            /// Call the script's `spawn_one(time)` function `per_second` times
+            /// per second and emit the particles it returns.
+            ///
+            /// Unlike emitting from `add`, which runs once per step, this
+            /// keeps the number of particles per second the same regardless
+            /// of the step rate. Both can be used at the same time. A rate of
+            /// 0, the default, turns this off.
+            fn set_emit_rate(per_second: f32) {
+                SETTINGS.lock().unwrap().emit_rate = per_second.max(0.0);
+            }
+
+            /// Keep at most `n` particles by removing the oldest ones after
+            /// new particles are emitted.
+            ///
+            /// Emission keeps going, but particles are removed before their
+            /// time. This counts all particles, however they were emitted,
+            /// while `emit_budgeted` limits the particles emitted per call
+            /// to `add`. A cap of 0 or less, the default, turns this off.
+            fn set_soft_cap(n: i32) {
+                SETTINGS.lock().unwrap().soft_cap = (n > 0).then_some(n as usize);
+            }
+
+            /// Limit the speed of every particle to `speed` units per call to
+            /// `update`.
+            ///
+            /// The velocity of each particle that `update` returns is
+            /// shortened to at most `speed`, keeping its direction. Since
+            /// the engine doesn't integrate velocities, this only limits
+            /// motion in scripts that move particles by their velocity. There
+            /// is no limit by default, and a negative speed turns it off.
+            fn set_max_speed(speed: f32) {
+                SETTINGS.lock().unwrap().max_speed = (speed >= 0.0).then_some(speed);
+            }
+
+            /// Draw every particle `multiplier` times as large as its scale.
+            ///
+            /// This only changes how large particles look. The scale that
+            /// scripts read and use for spacing or collisions stays the
+            /// same. The default is 1 and negative multipliers are treated
+            /// as 0.
+            fn set_render_scale(multiplier: f32) {
+                SETTINGS.lock().unwrap().render_scale = multiplier.max(0.0);
+            }
+
+            /// Fade particles out over the last `seconds` of their lifetime.
+            ///
+            /// The alpha is scaled down linearly to 0 at the moment the
+            /// particle expires. This only changes how particles are drawn,
+            /// the color that scripts read stays the same. Particles with an
+            /// infinite lifetime and particles that `update` removes by
+            /// returning `None` don't fade. The default of 0 turns this off.
+            fn set_fade_out(seconds: f32) {
+                SETTINGS.lock().unwrap().fade_out = seconds.max(0.0);
+            }
+
+            /// Fade particles towards `color` with their distance from the
+            /// camera.
+            ///
+            /// A higher `density` makes the fog thicker. A density of 0, the
+            /// default, turns fog off. The default color is the background
+            /// color.
+            fn set_fog(density: f32, color: Val<Color>) {
+                let mut settings = SETTINGS.lock().unwrap();
+                settings.fog_density = density.max(0.0);
+                settings.fog_color = color.0;
+            }
+
+            /// Random integer from `low` up to, but not including, `high`.
+            ///
+            /// Returns `low` if the range is empty.
+            fn rand_int(low: i32, high: i32) -> i32 {
+                if (low..high).is_empty() {
+                    return low;
+                }
+                RNG.lock().unwrap().random_range(low..high)
+            }
+
+            /// Draw points as solid discs with an edge that fades out over
+            /// `softness` times their radius.
+            ///
+            /// This only affects point mode. A softness of 0, the default,
+            /// draws points as glows instead. The softness is clamped to 0
+            /// to 1.
+            fn set_edge_softness(softness: f32) {
+                SETTINGS.lock().unwrap().edge_softness = softness.clamp(0.0, 1.0);
+            }
+
+            /// Number of instances drawn in the last frame.
+            ///
+            /// This is the actual rendering load. It currently equals the
+            /// number of live particles, but can be larger once particles
+            /// are drawn with more than one instance each.
+            fn instance_count() -> i32 {
+                ENGINE_INFO.lock().unwrap().instance_count as i32
+            }
+
+            /// Largest distance of any particle from the centroid of all
+            /// particles, or 0 if there are none.
+            ///
+            /// This is measured once per frame, so it lags up to a frame
+            /// behind.
+            fn bounding_radius() -> f32 {
+                ENGINE_INFO.lock().unwrap().bounding_radius
+            }
+
+            /// Time the oldest live particle has been alive for, or 0 if there
+            /// are no particles.
+            ///
+            /// This is measured once per frame, so it lags up to a frame
+            /// behind.
+            fn oldest_age() -> f32 {
+                ENGINE_INFO.lock().unwrap().oldest_age
+            }
+
+            /// Sum of `0.5 * mass * speed^2` over all particles, or 0 if
+            /// there are none.
+            ///
+            /// With the default mass of 1 this is half the sum of the
+            /// squared speeds. Watching it shows whether damping or
+            /// collisions lose energy as intended. This is measured once per
+            /// frame, so it lags up to a frame behind.
+            fn total_energy() -> f32 {
+                ENGINE_INFO.lock().unwrap().total_energy
+            }
+
+            /// Average velocity of all particles, or the zero vector if there
+            /// are none.
+            ///
+            /// Like `Particle.vel`, this is in units per call to `update`.
+            /// Its direction is the bulk flow of the particles. This is
+            /// measured once per frame, so it lags up to a frame behind.
+            fn mean_velocity() -> Val<Vec3> {
+                Val(ENGINE_INFO.lock().unwrap().mean_velocity)
+            }
+
+            /// Position of the particle closest to `p`.
+            ///
+            /// If there are no particles, or `p` is not finite, all
+            /// components are infinite. This only checks the particles
+            /// around `p`, so it is cheap when there are particles nearby.
+            /// It sees the particles as of the last frame, so particles
+            /// emitted or moved since then are not taken into account yet.
+            fn nearest_particle(p: Val<Vec3>) -> Val<Vec3> {
+                let mut info = ENGINE_INFO.lock().unwrap();
+                Val(info.spatial_hash.nearest(p.0).unwrap_or(Vec3::INFINITY))
+            }
+
+            /// Number of particles within `radius` of `p`.
+            ///
+            /// This only checks the particles near `p`, so it stays cheap for
+            /// small radii. Like `nearest_particle`, it sees the particles as
+            /// of the last frame.
+            fn density_at(p: Val<Vec3>, radius: f32) -> i32 {
+                let count = ENGINE_INFO.lock().unwrap().spatial_hash.count_within(p.0, radius);
+                count.min(i32::MAX as usize) as i32
+            }
+
+            /// Factor from 1 for particles at most `near` away from the
+            /// camera to 0 for particles at least `far` away.
+            ///
+            /// The factor falls off linearly in between. Multiplying the
+            /// alpha of a particle by it makes nearer particles stand out
+            /// without sorting them. If `near` is larger than `far` the two
+            /// are swapped. This uses the camera as of the last frame.
+            fn depth_fade(p: Val<Particle>, near: f32, far: f32) -> f32 {
+                let (near, far) = (near.min(far), near.max(far));
+                let distance = ENGINE_INFO.lock().unwrap().camera_position.distance(p.pos);
+                if near == far {
+                    return if distance <= near { 1.0 } else { 0.0 };
+                }
+                ((far - distance) / (far - near)).clamp(0.0, 1.0)
+            }
+
+            /// Whether the point `p` is inside the camera's view frustum.
+            ///
+            /// This uses the camera as of the last frame, so while the camera
+            /// moves the result lags a frame behind.
+            fn in_view(p: Val<Vec3>) -> bool {
+                let clip = ENGINE_INFO.lock().unwrap().clip_from_world * p.extend(1.0);
+                clip.w > 0.0
+                    && clip.x.abs() <= clip.w
+                    && clip.y.abs() <= clip.w
+                    && (0.0..=clip.w).contains(&clip.z)
+            }
+
+            /// How fast the particle moves across the screen, in normalized
+            /// device coordinates per call to `update`.
+            ///
+            /// The screen goes from -1 to 1 in both directions, with X to the
+            /// right and Y up, so a particle crossing the full width moves 2
+            /// units. This is the on-screen motion caused by the velocity of
+            /// the particle, with the camera as of the last frame. If the
+            /// particle or the point its velocity leads to is behind the
+            /// camera, the result is the zero vector.
+            fn screen_velocity(p: Val<Particle>) -> Val<Vec2> {
+                let clip_from_world = ENGINE_INFO.lock().unwrap().clip_from_world;
+                let from = to_ndc(clip_from_world, p.pos);
+                let to = to_ndc(clip_from_world, p.pos + p.vel);
+                Val(from.zip(to).map_or(Vec2::ZERO, |(from, to)| to - from))
+            }
+
+            /// Color of `gradient` at the height of `p` on the screen.
+            ///
+            /// The gradient is sampled at 0 at the bottom of the screen and
+            /// at 1 at the top, with the camera as of the last frame. Points
+            /// above or below the screen get the color at the nearest edge,
+            /// and points behind the camera the color at the bottom.
+            fn screen_gradient_color(p: Val<Vec3>, gradient: Val<ColorGradient>) -> Val<Color> {
+                let clip_from_world = ENGINE_INFO.lock().unwrap().clip_from_world;
+                let t = to_ndc(clip_from_world, p.0).map_or(0.0, |ndc| (ndc.y + 1.0) / 2.0);
+                Val(Color::from(gradient.sample(t.clamp(0.0, 1.0))))
+            }
+
+            /// Direction held on the keyboard.
+            ///
+            /// A and D move along X, Q and E along Y, and W and S along Z,
+            /// with W pointing towards -Z like the default camera. Each
+            /// component is -1, 0 or 1, so the vector is not normalized.
+            fn move_axis() -> Val<Vec3> {
+                Val(ENGINE_INFO.lock().unwrap().move_axis)
+            }
+
+            /// Speed of the simulation relative to real time.
+            ///
+            /// The times passed to `add` and `update` already run at this
+            /// speed, so effects that should ignore slow motion can use it to
+            /// compensate.
+            fn time_scale() -> f32 {
+                ENGINE_INFO.lock().unwrap().time_scale
+            }
+
+            /// Seconds since the previous step in which `update` was
+            /// called.
+            ///
+            /// By default `update` runs at a fixed rate and this is always
+            /// the same. With `--frame-update` it runs every frame and this
+            /// is the frame time, so scaling motion by it keeps the speed
+            /// independent of the frame rate.
+            fn delta_time() -> f32 {
+                ENGINE_INFO.lock().unwrap().update_delta
+            }
+
+            /// Fraction of a fixed step that the last rendered frame was
+            /// ahead of the last fixed step, from 0 to 1.
+            ///
+            /// Blending the states of the last two steps by this fraction
+            /// gives smooth motion at any frame rate. This is only
+            /// meaningful for state that is rendered interpolated, the
+            /// engine itself draws particles as of the last step. Like the
+            /// other engine information, it lags a frame behind.
+            fn interpolation_alpha() -> f32 {
+                ENGINE_INFO.lock().unwrap().interpolation_alpha
+            }
+
+            /// Position of an axis of the first connected gamepad.
+            ///
+            /// The axes are numbered 0: left stick X, 1: left stick Y,
+            /// 2: left trigger, 3: right stick X, 4: right stick Y,
+            /// 5: right trigger. Sticks go from -1 to 1, with up and right
+            /// positive, and triggers from 0 when released to 1 when fully
+            /// pressed. Returns 0 for other numbers, when no gamepad is
+            /// connected, or when the gamepad doesn't have the axis.
+            fn gamepad_axis(axis: i32) -> f32 {
+                let info = ENGINE_INFO.lock().unwrap();
+                usize::try_from(axis)
+                    .ok()
+                    .and_then(|i| info.gamepad_axes.get(i).copied())
+                    .unwrap_or(0.0)
+            }
+
+            /// Whether a button of the first connected gamepad is held.
+            ///
+            /// The buttons are numbered 0: south (A on Xbox controllers),
+            /// 1: east, 2: north, 3: west, 4: C, 5: Z, 6: left bumper,
+            /// 7: left trigger, 8: right bumper, 9: right trigger,
+            /// 10: select, 11: start, 12: mode, 13: left stick,
+            /// 14: right stick, 15: D-pad up, 16: D-pad down,
+            /// 17: D-pad left, 18: D-pad right. Returns false for other
+            /// numbers or when no gamepad is connected.
+            fn gamepad_button(button: i32) -> bool {
+                let info = ENGINE_INFO.lock().unwrap();
+                usize::try_from(button)
+                    .ok()
+                    .and_then(|i| info.gamepad_buttons.get(i).copied())
+                    .unwrap_or(false)
+            }
+
+            /// Whether the primary window currently has focus.
+            fn window_focused() -> bool {
+                ENGINE_INFO.lock().unwrap().window_focused
+            }
+
+            /// Number of times the script has been compiled successfully,
+            /// including the current version.
+            fn reload_count() -> i32 {
+                RELOAD_COUNT.load(Ordering::Relaxed) as i32
+            }
+
+            /// Number of fixed steps since the current version of the
+            /// script was compiled.
+            ///
+            /// This is 0 during the first step with the new version. Unlike
+            /// the time, it is the same on every run regardless of the frame
+            /// rate, so it suits sequences that should restart on reload.
+            fn steps_since_reload() -> i32 {
+                STEPS_SINCE_RELOAD.load(Ordering::Relaxed).min(i32::MAX as u32) as i32
+            }
+
+            /// Convert an angle in degrees to radians.
+            fn radians(degrees: f32) -> f32 {
+                degrees.to_radians()
+            }
+
+            /// Convert an angle in radians to degrees.
+            fn degrees(radians: f32) -> f32 {
+                radians.to_degrees()
+            }
+
+            /// Smooth oscillation between 0 and 1 that repeats every
+            /// `period` seconds.
+            ///
+            /// It starts at 0 when `t` is 0 and peaks at 1 halfway through
+            /// the period. Returns 0 if `period` is not positive.
+            fn pulse(t: f32, period: f32) -> f32 {
+                if period <= 0.0 {
+                    return 0.0;
+                }
+                0.5 - 0.5 * (std::f32::consts::TAU * t / period).cos()
             }
 
             impl Val<Particle> {
-                fn new(pos: Val<Vec3>, scale: f32, color: Val<Color>) -> Self {
-                    Val(Particle { pos: pos.0, scale, color: color.0 })
+                /// A particle with the default kind, mass, lifetime and
+                /// rotation.
+                ///
+                /// In `update`, change the particle that was passed in with
+                /// the `with_` methods instead, which keeps the fields that
+                /// aren't changed.
+                fn new(pos: Val<Vec3>, vel: Val<Vec3>, scale: f32, color: Val<Color>) -> Self {
+                    Val(Particle::new(pos.0, vel.0, scale, color.0))
+                }
+
+                /// Id assigned when the particle was emitted.
+                ///
+                /// Ids increase monotonically per emitted particle. This
+                /// accessor truncates the id to an `i32`, so it wraps around
+                /// to negative values after 2^31 emitted particles.
+                fn id(self) -> i32 {
+                    self.id as i32
                 }
 
                 fn pos(self) -> Val<Vec3> {
                     Val(self.pos)
                 }
 
+                fn with_pos(self, pos: Val<Vec3>) -> Self {
+                    Val(Particle { pos: pos.0, ..self.0 })
+                }
+
+                /// Velocity of this particle, in units per call to `update`.
+                ///
+                /// The engine doesn't apply the velocity, `update` has to
+                /// integrate it, for example with `p.pos().add(p.vel())`.
+                fn vel(self) -> Val<Vec3> {
+                    Val(self.vel)
+                }
+
+                fn with_vel(self, vel: Val<Vec3>) -> Self {
+                    Val(Particle { vel: vel.0, ..self.0 })
+                }
+
                 fn scale(self) -> f32 {
                     self.scale
                 }
 
+                fn with_scale(self, scale: f32) -> Self {
+                    Val(Particle { scale, ..self.0 })
+                }
+
                 fn color(self) -> Val<Color> {
                     Val(self.color)
                 }
+
+                fn with_color(self, color: Val<Color>) -> Self {
+                    Val(Particle { color: color.0, ..self.0 })
+                }
+
+                /// Script-defined kind of this particle, 0 by default.
+                ///
+                /// The engine doesn't use the kind, so scripts can use it to
+                /// handle several types of particles in one `update`.
+                fn kind(self) -> i32 {
+                    self.kind
+                }
+
+                fn with_kind(self, kind: i32) -> Self {
+                    Val(Particle { kind, ..self.0 })
+                }
+
+                /// Mass of this particle, 1 by default.
+                ///
+                /// Scripts applying a force should divide it by the mass to
+                /// get the acceleration.
+                fn mass(self) -> f32 {
+                    self.mass
+                }
+
+                /// Set the mass of this particle.
+                ///
+                /// Masses that are zero, negative or NaN are replaced by a
+                /// tiny positive mass, so dividing by the mass is always safe.
+                fn with_mass(self, mass: f32) -> Self {
+                    Val(Particle { mass: mass.max(Particle::MIN_MASS), ..self.0 })
+                }
+
+                /// Age in seconds after which the engine removes this
+                /// particle, infinite by default.
+                ///
+                /// The particle is removed after the `update` call in which
+                /// its age exceeds the lifetime of the particle that `update`
+                /// returned, even if it returned `Some`. Returning `None`
+                /// still removes a particle right away. A particle built with
+                /// `Particle.new` has an infinite lifetime, so `update` should
+                /// return the particle it was passed, changed with the `with_`
+                /// methods, to keep it.
+                fn lifetime(self) -> f32 {
+                    self.lifetime
+                }
+
+                /// Set the lifetime of this particle. Negative lifetimes are
+                /// treated as 0.
+                fn with_lifetime(self, lifetime: f32) -> Self {
+                    Val(Particle { lifetime: lifetime.max(0.0), ..self.0 })
+                }
+
+                /// Orientation of this particle, no rotation by default.
+                ///
+                /// This rotates the mesh drawn for the particle around its
+                /// center. Points always face the camera, so they ignore it.
+                fn rotation(self) -> Val<Quat> {
+                    Val(self.rotation)
+                }
+
+                fn with_rotation(self, rotation: Val<Quat>) -> Self {
+                    Val(Particle { rotation: rotation.0, ..self.0 })
+                }
             }
 
             impl Val<Vec3> {
@@ -62,10 +842,54 @@ impl ScriptManager {
                     Val(Vec3 { x, y, z })
                 }
 
+                fn zero() -> Self {
+                    Val(Vec3::ZERO)
+                }
+
+                fn one() -> Self {
+                    Val(Vec3::ONE)
+                }
+
+                fn up() -> Self {
+                    Val(Vec3::Y)
+                }
+
+                fn down() -> Self {
+                    Val(Vec3::NEG_Y)
+                }
+
+                fn left() -> Self {
+                    Val(Vec3::NEG_X)
+                }
+
+                fn right() -> Self {
+                    Val(Vec3::X)
+                }
+
+                /// Point at `radius` from the Y axis and `height` along it.
+                ///
+                /// The angle is measured in radians in the XZ plane, starting
+                /// at +X and turning towards +Z.
+                fn from_polar(radius: f32, angle: f32, height: f32) -> Self {
+                    Val(Vec3::new(radius * angle.cos(), height, radius * angle.sin()))
+                }
+
                 fn add(self, other: Self) -> Self {
                     Val(self.0 + other.0)
                 }
 
+                fn sub(self, other: Self) -> Self {
+                    Val(self.0 - other.0)
+                }
+
+                fn dot(self, other: Self) -> f32 {
+                    self.0.dot(other.0)
+                }
+
+                fn cross(self, other: Self) -> Self {
+                    Val(self.0.cross(other.0))
+                }
+
                 fn x(self) -> f32 {
                     self.x
                 }
@@ -82,13 +906,257 @@ impl ScriptManager {
                     self.length()
                 }
 
+                fn distance(self, other: Self) -> f32 {
+                    self.0.distance(other.0)
+                }
+
+                /// Squared distance to `other`.
+                ///
+                /// This avoids the square root in `distance`, so prefer it
+                /// when only comparing distances, for example against a
+                /// squared radius.
+                fn distance_squared(self, other: Self) -> f32 {
+                    self.0.distance_squared(other.0)
+                }
+
                 fn normalize(self) -> Self {
                     Val(self.normalize())
                 }
 
+                /// Scale the vector so its length lies between `min` and
+                /// `max`, keeping its direction.
+                ///
+                /// If `min` is larger than `max` the two are swapped and
+                /// negative bounds are treated as 0. The zero vector has no
+                /// direction, so it is returned unchanged.
+                fn clamp_length(self, min: f32, max: f32) -> Self {
+                    if self.0 == Vec3::ZERO {
+                        return self;
+                    }
+                    let (min, max) = (min.min(max).max(0.0), min.max(max).max(0.0));
+                    Val(self.0.clamp_length(min, max))
+                }
+
+                /// Shorten the vector to at most `max`, keeping its direction.
+                ///
+                /// Useful to cap the speed of a particle. A negative `max`
+                /// is treated as 0.
+                fn clamp_length_max(self, max: f32) -> Self {
+                    Val(self.0.clamp_length_max(max.max(0.0)))
+                }
+
                 fn scale(self, r: f32) -> Self {
                     Val(self.0 * r)
                 }
+
+                /// Multiply component by component.
+                ///
+                /// Unlike `scale`, this can stretch or squash along each axis
+                /// separately.
+                fn mul(self, other: Self) -> Self {
+                    Val(self.0 * other.0)
+                }
+
+                /// Sum of this vector scaled by `a_weight` and `other` scaled
+                /// by `b_weight`.
+                ///
+                /// Handy to blend steering forces, for example cohesion and
+                /// separation. The weights don't need to add up to 1.
+                fn weighted_add(self, a_weight: f32, other: Self, b_weight: f32) -> Self {
+                    Val(self.0 * a_weight + other.0 * b_weight)
+                }
+
+                fn neg(self) -> Self {
+                    Val(-self.0)
+                }
+
+                /// Divide every component by `r`.
+                ///
+                /// Dividing by 0 does not fail, but gives infinite (or NaN)
+                /// components.
+                fn div(self, r: f32) -> Self {
+                    Val(self.0 / r)
+                }
+
+                /// Linear interpolation from this vector at `t = 0` to `other`
+                /// at `t = 1`.
+                ///
+                /// `t` is not clamped, so values outside 0 to 1 extrapolate.
+                fn lerp(self, other: Self, t: f32) -> Self {
+                    Val(self.0.lerp(other.0, t))
+                }
+
+                /// Angle in radians to turn this vector to `other` around
+                /// `axis`, between -pi and pi.
+                ///
+                /// Both vectors are first projected onto the plane
+                /// perpendicular to the axis. The angle is positive for a
+                /// counterclockwise turn when looking against the axis, that
+                /// is, when the axis points at the viewer, following the
+                /// right-hand rule. If either projection or the axis is zero,
+                /// the angle is 0.
+                fn signed_angle(self, other: Self, axis: Self) -> f32 {
+                    let Some(axis) = axis.try_normalize() else {
+                        return 0.0;
+                    };
+                    let a = self.0.reject_from_normalized(axis);
+                    let b = other.0.reject_from_normalized(axis);
+                    axis.dot(a.cross(b)).atan2(a.dot(b))
+                }
+
+                /// Normalized direction from this point to `target`.
+                ///
+                /// Returns the zero vector if both points coincide.
+                fn direction_to(self, target: Self) -> Self {
+                    Val((target.0 - self.0).normalize_or_zero())
+                }
+
+                /// Round each component to the nearest multiple of `cell`.
+                ///
+                /// A `cell` of 0 returns the vector unchanged.
+                fn snap(self, cell: f32) -> Self {
+                    if cell == 0.0 {
+                        return self;
+                    }
+                    Val((self.0 / cell).round() * cell)
+                }
+
+                /// Like `snap`, but with a separate cell size per axis.
+                ///
+                /// Axes with a cell size of 0 are left unchanged.
+                fn snap_each(self, cell: Self) -> Self {
+                    let snapped = (self.0 / cell.0).round() * cell.0;
+                    Val(Vec3::select(cell.cmpeq(Vec3::ZERO), self.0, snapped))
+                }
+
+                /// Reflect this vector off a surface with the given normal,
+                /// for example to bounce a velocity off a floor.
+                ///
+                /// The normal must be normalized. Otherwise the result is
+                /// scaled along the normal by its squared length and is not a
+                /// proper reflection.
+                fn reflect(self, normal: Self) -> Self {
+                    Val(self.0.reflect(normal.0))
+                }
+            }
+
+            impl Val<Vec2> {
+                fn new(x: f32, y: f32) -> Self {
+                    Val(Vec2 { x, y })
+                }
+
+                fn x(self) -> f32 {
+                    self.x
+                }
+
+                fn y(self) -> f32 {
+                    self.y
+                }
+
+                fn length(self) -> f32 {
+                    self.length()
+                }
+
+                fn normalize(self) -> Self {
+                    Val(self.normalize())
+                }
+
+                /// Turn this into a `Vec3` with the given `z` component.
+                ///
+                /// Use `Vec3.new(v.x(), height, v.y())` instead to place a 2D
+                /// shape on the horizontal XZ plane.
+                fn extend(self, z: f32) -> Val<Vec3> {
+                    Val(self.0.extend(z))
+                }
+            }
+
+            impl Val<Quat> {
+                /// Rotation by `angle` radians around `axis`.
+                ///
+                /// The axis doesn't need to be normalized. A zero axis gives
+                /// no rotation.
+                fn from_axis_angle(axis: Val<Vec3>, angle: f32) -> Self {
+                    match axis.try_normalize() {
+                        Some(axis) => Val(Quat::from_axis_angle(axis, angle)),
+                        None => Val(Quat::IDENTITY),
+                    }
+                }
+
+                /// Rotate `v` by this rotation.
+                fn mul_vec3(self, v: Val<Vec3>) -> Val<Vec3> {
+                    Val(self.0.mul_vec3(v.0))
+                }
+
+                /// Turn this rotation towards `target` by at most
+                /// `max_radians`, without going past it.
+                ///
+                /// Calling this every step with the same maximum turns at a
+                /// fixed rate, so it suits followers that can't turn
+                /// instantly. If the rotations are already within
+                /// `max_radians` of each other, or already aligned, this
+                /// returns `target`. A negative maximum is treated as 0.
+                fn rotate_toward(self, target: Self, max_radians: f32) -> Self {
+                    Val(self.0.rotate_towards(target.0, max_radians.max(0.0)))
+                }
+            }
+
+            impl Val<ColorGradient> {
+                /// Gradient from `a` at 0 to `b` at 1.
+                fn new(a: Val<Color>, b: Val<Color>) -> Self {
+                    Val(ColorGradient {
+                        stops: vec![(0.0, a.to_linear()), (1.0, b.to_linear())],
+                    })
+                }
+
+                /// This gradient with an extra color `c` at position `t`.
+                ///
+                /// Stops can be added in any order and outside 0 to 1. A stop
+                /// at the same position as an existing one goes after it,
+                /// which gives a sharp change in color there.
+                fn add_stop(self, t: f32, c: Val<Color>) -> Self {
+                    let mut gradient = self.0;
+                    if !t.is_nan() {
+                        let i = gradient.stops.partition_point(|(position, _)| *position <= t);
+                        gradient.stops.insert(i, (t, c.to_linear()));
+                    }
+                    Val(gradient)
+                }
+
+                /// Color at position `t`, blended in linear RGB.
+                ///
+                /// Positions before the first stop or after the last get the
+                /// color of that stop.
+                fn sample(self, t: f32) -> Val<Color> {
+                    Val(Color::from(self.0.sample(t)))
+                }
+
+                /// Color at a random position from 0 to 1, blended in linear
+                /// RGB like `sample`.
+                ///
+                /// Every position is equally likely, so wider segments of the
+                /// gradient come up more often. Stops outside 0 to 1 only
+                /// affect the colors near the ends.
+                fn sample_random(self) -> Val<Color> {
+                    let t = RNG.lock().unwrap().random();
+                    Val(Color::from(self.0.sample(t)))
+                }
+            }
+
+            impl Val<ScriptRng> {
+                /// A random number generator that always produces the same
+                /// numbers for the same seed.
+                fn seeded(seed: i64) -> Self {
+                    Val(ScriptRng(Arc::new(Mutex::new(StdRng::seed_from_u64(seed as u64)))))
+                }
+
+                /// Next random number from `low` up to, but not including,
+                /// `high`. Returns `low` if the range is empty.
+                fn next(self, low: f32, high: f32) -> f32 {
+                    if (low..high).is_empty() {
+                        return low;
+                    }
+                    self.0.0.lock().unwrap().random_range(low..high)
+                }
             }
 
             impl Val<Color> {
@@ -96,6 +1164,26 @@ impl ScriptManager {
                     Val(Color::from(Srgba::RED))
                 }
 
+                fn green() -> Self {
+                    Val(Color::from(Srgba::GREEN))
+                }
+
+                fn blue() -> Self {
+                    Val(Color::from(Srgba::BLUE))
+                }
+
+                fn yellow() -> Self {
+                    Val(Color::from(palettes::basic::YELLOW))
+                }
+
+                fn white() -> Self {
+                    Val(Color::from(Srgba::WHITE))
+                }
+
+                fn black() -> Self {
+                    Val(Color::from(Srgba::BLACK))
+                }
+
                 fn none() -> Self {
                     Val(Color::from(Srgba::NONE))
                 }
@@ -104,15 +1192,52 @@ impl ScriptManager {
                     Val(Color::from(Srgba::new(r, g, b, 1.0)))
                 }
 
+                /// Color from a hue in degrees, a saturation and a value.
+                ///
+                /// The hue goes from 0 (red) through 120 (green) and 240
+                /// (blue) back to red at 360. Hues outside that range wrap
+                /// around, so a hue can keep increasing over time. Saturation
+                /// and value go from 0 to 1.
+                fn hsv(h: f32, s: f32, v: f32) -> Self {
+                    Val(Color::hsv(h.rem_euclid(360.0), s, v))
+                }
+
                 fn mix(t: f32, x: Self, y: Self) -> Self {
                     Val(x.mix(&y, t))
                 }
+
+                /// This color with its alpha replaced by `a`, from 0 for
+                /// invisible to 1 for opaque.
+                fn with_alpha(self, a: f32) -> Self {
+                    Val(self.0.with_alpha(a.clamp(0.0, 1.0)))
+                }
+
+                fn alpha(self) -> f32 {
+                    self.0.alpha()
+                }
+
+                /// Red component in sRGB, like the components passed to
+                /// `Color.new`.
+                fn r(self) -> f32 {
+                    self.to_srgba().red
+                }
+
+                /// Green component in sRGB, like the components passed to
+                /// `Color.new`.
+                fn g(self) -> f32 {
+                    self.to_srgba().green
+                }
+
+                /// Blue component in sRGB, like the components passed to
+                /// `Color.new`.
+                fn b(self) -> f32 {
+                    self.to_srgba().blue
+                }
             }
 
             impl f32 {
                 fn rand(low: f32, high: f32) -> f32 {
-                    let mut rng = rand::rng();
-                    rng.random_range(low..high)
+                    RNG.lock().unwrap().random_range(low..high)
                 }
 
                 fn sin(self) -> Self {
@@ -123,9 +1248,135 @@ impl ScriptManager {
                     self.cos()
                 }
 
+                fn tan(self) -> Self {
+                    self.tan()
+                }
+
+                /// Angle in radians from the positive X axis to the point
+                /// `(x, self)`, between -pi and pi.
+                ///
+                /// Call it as `y.atan2(x)`, like in Rust.
+                fn atan2(self, x: f32) -> Self {
+                    self.atan2(x)
+                }
+
+                /// Inverse sine in radians. Numbers outside -1 to 1 give NaN.
+                fn asin(self) -> Self {
+                    self.asin()
+                }
+
+                /// Inverse cosine in radians. Numbers outside -1 to 1 give
+                /// NaN.
+                fn acos(self) -> Self {
+                    self.acos()
+                }
+
+                fn exp(self) -> Self {
+                    self.exp()
+                }
+
+                /// Natural logarithm. Negative numbers give NaN and 0 gives
+                /// negative infinity.
+                fn ln(self) -> Self {
+                    self.ln()
+                }
+
+                /// Square root. Negative numbers give NaN.
+                fn sqrt(self) -> Self {
+                    self.sqrt()
+                }
+
+                /// Raise to the power `n`.
+                fn powf(self, n: f32) -> Self {
+                    self.powf(n)
+                }
+
+                /// Raise to the integer power `n`, which may be negative.
+                ///
+                /// This is usually faster than `powf` for small powers.
+                fn powi(self, n: i32) -> Self {
+                    self.powi(n)
+                }
+
+                /// Linear interpolation from this number at `t = 0` to `b` at
+                /// `t = 1`.
+                ///
+                /// `t` is not clamped, so values outside 0 to 1 extrapolate.
+                fn lerp(self, b: f32, t: f32) -> Self {
+                    self + (b - self) * t
+                }
+
+                /// Smooth transition from 0 when `x` is at `edge0` to 1 when
+                /// it is at `edge1`.
+                ///
+                /// The result is clamped to 0 to 1 and eases in and out with
+                /// the Hermite curve `3t^2 - 2t^3`. If both edges are equal,
+                /// this jumps from 0 to 1 at the edge.
+                fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+                    if edge0 == edge1 {
+                        return if x < edge0 { 0.0 } else { 1.0 };
+                    }
+                    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                }
+
+                fn min(self, other: f32) -> Self {
+                    self.min(other)
+                }
+
+                fn max(self, other: f32) -> Self {
+                    self.max(other)
+                }
+
+                /// Restrict to the range from `lo` to `hi`.
+                ///
+                /// If `lo` is larger than `hi` the two are swapped, where Rust's
+                /// `clamp` would panic. NaN bounds are ignored.
+                fn clamp(self, lo: f32, hi: f32) -> Self {
+                    let (lo, hi) = if lo > hi { (hi, lo) } else { (lo, hi) };
+                    self.max(lo).min(hi)
+                }
+
+                fn floor(self) -> Self {
+                    self.floor()
+                }
+
+                fn ceil(self) -> Self {
+                    self.ceil()
+                }
+
+                /// Round to the nearest integer, with halfway cases rounded
+                /// away from 0.
+                fn round(self) -> Self {
+                    self.round()
+                }
+
+                /// Round towards 0, dropping the fractional part.
+                fn trunc(self) -> Self {
+                    self.trunc()
+                }
+
+                /// Fractional part, with the same sign as the number.
+                ///
+                /// This is `self - self.trunc()`, so `-1.25` gives `-0.25`.
+                fn fract(self) -> Self {
+                    self.fract()
+                }
+
                 fn pi() -> f32 {
                     std::f32::consts::PI
                 }
+
+                /// A full turn in radians, `2 * pi`.
+                fn tau() -> f32 {
+                    std::f32::consts::TAU
+                }
+
+                /// 1 for positive numbers and `0.0`, -1 for negative numbers
+                /// and `-0.0`. NaN gives NaN.
+                fn sign(self) -> Self {
+                    self.signum()
+                }
             }
         };
 
@@ -137,15 +1388,54 @@ impl ScriptManager {
             path: path.to_path_buf(),
             last_compile: SystemTime::UNIX_EPOCH,
             script_not_found_logged: false,
+            reload_count: 0,
             update: None,
             update_ms: 0.0,
             add: None,
             add_ms: 0.0,
+            spawn_one: None,
+            emit_accumulator: 0.0,
         }
     }
 
+    /// Print the documentation of the script library to stdout.
+    ///
+    /// Roto writes the documentation as files into a directory, so they are
+    /// written to a temporary directory first and then printed in order.
+    pub fn print_docs(&self) -> std::io::Result<()> {
+        let dir = std::env::temp_dir().join("roto-demo-docs");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        self.runtime.print_documentation(&dir)?;
+        print_dir(&dir)
+    }
+
+    /// Play a different script, starting from the next reload.
+    ///
+    /// The functions of the current script are dropped right away, so
+    /// functions that the new script doesn't define don't keep running.
+    pub fn switch_script(&mut self, path: &Path) {
+        self.path = path.to_path_buf();
+        self.last_compile = SystemTime::UNIX_EPOCH;
+        self.script_not_found_logged = false;
+        self.stop();
+    }
+
+    /// Stop running the current script until it is modified.
+    pub fn stop(&mut self) {
+        self.update = None;
+        self.add = None;
+        self.spawn_one = None;
+        self.emit_accumulator = 0.0;
+    }
+
     /// Check the modification time of the script and reload it if it is outdated.
+    ///
+    /// This is called once per fixed step, which also advances the step
+    /// counter the script can read.
     pub fn reload(&mut self) {
+        STEPS_SINCE_RELOAD.fetch_add(1, Ordering::Relaxed);
+
         let res = std::fs::metadata(&self.path);
 
         let modified = match res.and_then(|md| md.modified()) {
@@ -181,6 +1471,11 @@ impl ScriptManager {
             }
         };
 
+        self.reload_count += 1;
+        *SETTINGS.lock().unwrap() = Settings::DEFAULT;
+        RELOAD_COUNT.store(self.reload_count, Ordering::Relaxed);
+        STEPS_SINCE_RELOAD.store(0, Ordering::Relaxed);
+
         if let Ok(update) = pkg.get_function("update") {
             self.update = Some(update);
         }
@@ -188,5 +1483,25 @@ impl ScriptManager {
         if let Ok(add) = pkg.get_function("add") {
             self.add = Some(add);
         }
+
+        if let Ok(spawn_one) = pkg.get_function("spawn_one") {
+            self.spawn_one = Some(spawn_one);
+        }
+    }
+}
+
+/// Print every file in `dir` and its subdirectories, sorted by path.
+fn print_dir(dir: &Path) -> std::io::Result<()> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            print_dir(&path)?;
+        } else {
+            println!("{}", std::fs::read_to_string(&path)?);
+        }
     }
+    Ok(())
 }
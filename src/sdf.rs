@@ -0,0 +1,103 @@
+use std::{path::Path, sync::OnceLock};
+
+use bevy::math::{FloatExt, UVec3, Vec3};
+
+/// The signed distance field passed with `--sdf`, if any.
+pub static SDF: OnceLock<Sdf> = OnceLock::new();
+
+/// A signed distance field sampled on a uniform grid.
+///
+/// The file format is plain text with whitespace separated numbers:
+///
+/// ```text
+/// nx ny nz
+/// min_x min_y min_z
+/// cell_size
+/// d_0 d_1 d_2 ...
+/// ```
+///
+/// followed by `nx * ny * nz` distances, with x varying fastest, then y,
+/// then z. Grid point `(i, j, k)` sits at `min + (i, j, k) * cell_size`.
+pub struct Sdf {
+    size: UVec3,
+    min: Vec3,
+    cell_size: f32,
+    distances: Vec<f32>,
+}
+
+impl Sdf {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut numbers = text.split_whitespace();
+        let mut next = |what: &str| -> Result<f32, String> {
+            let s = numbers.next().ok_or_else(|| format!("missing {what}"))?;
+            s.parse().map_err(|_| format!("invalid {what}: {s}"))
+        };
+
+        let mut size = [0; 3];
+        for n in &mut size {
+            let v = next("grid size")?;
+            if v < 2.0 || v.fract() != 0.0 {
+                return Err(format!(
+                    "grid size must be an integer of at least 2, got {v}"
+                ));
+            }
+            *n = v as u32;
+        }
+        let size = UVec3::from_array(size);
+
+        let min = Vec3::new(next("minimum")?, next("minimum")?, next("minimum")?);
+
+        let cell_size = next("cell size")?;
+        if cell_size <= 0.0 {
+            return Err(format!("cell size must be positive, got {cell_size}"));
+        }
+
+        let count = size.x as usize * size.y as usize * size.z as usize;
+        let distances = (0..count)
+            .map(|_| next("distance"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            size,
+            min,
+            cell_size,
+            distances,
+        })
+    }
+
+    fn at(&self, x: u32, y: u32, z: u32) -> f32 {
+        let i =
+            (z as usize * self.size.y as usize + y as usize) * self.size.x as usize + x as usize;
+        self.distances[i]
+    }
+
+    /// Trilinearly interpolated distance at `p`.
+    ///
+    /// Points outside the grid are reported as being very far away.
+    pub fn sample(&self, p: Vec3) -> f32 {
+        let g = (p - self.min) / self.cell_size;
+        let max = (self.size - UVec3::ONE).as_vec3();
+        if g.cmplt(Vec3::ZERO).any() || g.cmpgt(max).any() {
+            return f32::MAX;
+        }
+
+        // Clamp the lower corner so points on the far faces still have a
+        // cell to interpolate in.
+        let i = g.floor().min(max - Vec3::ONE).as_uvec3();
+        let f = g - i.as_vec3();
+
+        let c = |dx, dy, dz| self.at(i.x + dx, i.y + dy, i.z + dz);
+        let x00 = c(0, 0, 0).lerp(c(1, 0, 0), f.x);
+        let x10 = c(0, 1, 0).lerp(c(1, 1, 0), f.x);
+        let x01 = c(0, 0, 1).lerp(c(1, 0, 1), f.x);
+        let x11 = c(0, 1, 1).lerp(c(1, 1, 1), f.x);
+        let y0 = x00.lerp(x10, f.y);
+        let y1 = x01.lerp(x11, f.y);
+        y0.lerp(y1, f.z)
+    }
+}
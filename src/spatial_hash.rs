@@ -0,0 +1,184 @@
+use bevy::math::{IVec3, Vec3};
+
+/// Size of the cells the particles are sorted into.
+const CELL_SIZE: f32 = 1.0;
+
+/// Points sorted by the grid cell they are in, to find the points near a
+/// position without checking all of them.
+///
+/// Sorting by cell keeps the points of cells that only differ in Z next to
+/// each other, so a query looks up one run of points per X and Y cell it
+/// overlaps. The points are only sorted by the first query after they
+/// change, so the hash can be kept up to date cheaply when nothing queries
+/// it.
+pub struct SpatialHash {
+    entries: Vec<([i32; 3], Vec3)>,
+    sorted: bool,
+}
+
+impl SpatialHash {
+    pub const EMPTY: Self = Self {
+        entries: Vec::new(),
+        sorted: true,
+    };
+
+    /// Replace the points in the hash, reusing its allocation.
+    pub fn rebuild(&mut self, points: impl Iterator<Item = Vec3>) {
+        self.entries.clear();
+        self.entries.extend(points.map(|p| (cell(p).to_array(), p)));
+        self.sorted = false;
+    }
+
+    fn sort(&mut self) {
+        if !self.sorted {
+            self.entries.sort_unstable_by_key(|(cell, _)| *cell);
+            self.sorted = true;
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    /// Number of points within `radius` of `p`, including points exactly at
+    /// the radius.
+    pub fn count_within(&mut self, p: Vec3, radius: f32) -> usize {
+        if radius < 0.0 || radius.is_nan() {
+            return 0;
+        }
+        self.sort();
+
+        let mut count = 0;
+        self.for_each_near(p, radius, |q| {
+            if q.distance_squared(p) <= radius * radius {
+                count += 1;
+            }
+        });
+        count
+    }
+
+    /// The point closest to `p`, or `None` if there are no points.
+    ///
+    /// This looks in a box around `p` that doubles in size until it contains
+    /// a point that is closer than any point outside of it.
+    pub fn nearest(&mut self, p: Vec3) -> Option<Vec3> {
+        if self.entries.is_empty() || !p.is_finite() {
+            return None;
+        }
+        self.sort();
+
+        let mut radius = CELL_SIZE;
+        loop {
+            let mut nearest = None;
+            let mut nearest_distance = f32::INFINITY;
+            let checked_all = self.for_each_near(p, radius, |q| {
+                let distance = q.distance_squared(p);
+                if distance < nearest_distance {
+                    nearest = Some(q);
+                    nearest_distance = distance;
+                }
+            });
+            if checked_all || nearest_distance <= radius * radius {
+                return nearest;
+            }
+            radius *= 2.0;
+        }
+    }
+
+    /// Call `f` for at least every point within `radius` of `p`, and return
+    /// whether it was called for all points.
+    fn for_each_near(&self, p: Vec3, radius: f32, mut f: impl FnMut(Vec3)) -> bool {
+        let min = cell(p - radius);
+        let max = cell(p + radius);
+        let columns =
+            (max.x as i64 - min.x as i64 + 1).saturating_mul(max.y as i64 - min.y as i64 + 1);
+
+        // For a large radius, looking up every column of cells is slower than
+        // checking every point.
+        if columns > self.entries.len() as i64 {
+            self.entries.iter().for_each(|(_, q)| f(*q));
+            return true;
+        }
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let start = self.entries.partition_point(|(c, _)| *c < [x, y, min.z]);
+                let end = self.entries.partition_point(|(c, _)| *c <= [x, y, max.z]);
+                self.entries[start..end].iter().for_each(|(_, q)| f(*q));
+            }
+        }
+        false
+    }
+}
+
+fn cell(p: Vec3) -> IVec3 {
+    (p / CELL_SIZE).floor().as_ivec3()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn random_points(rng: &mut StdRng, n: usize, extent: f32) -> Vec<Vec3> {
+        (0..n)
+            .map(|_| {
+                Vec3::new(
+                    rng.random_range(-extent..extent),
+                    rng.random_range(-extent..extent),
+                    rng.random_range(-extent..extent),
+                )
+            })
+            .collect()
+    }
+
+    fn hash(points: &[Vec3]) -> SpatialHash {
+        let mut hash = SpatialHash::EMPTY;
+        hash.rebuild(points.iter().copied());
+        hash
+    }
+
+    #[test]
+    fn count_within_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let points = random_points(&mut rng, 500, 10.0);
+        let mut hash = hash(&points);
+
+        for query in random_points(&mut rng, 100, 15.0) {
+            for radius in [0.0, 0.5, 1.0, 2.7, 8.0, 50.0] {
+                let expected = points
+                    .iter()
+                    .filter(|q| q.distance_squared(query) <= radius * radius)
+                    .count();
+                assert_eq!(hash.count_within(query, radius), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for (n, extent) in [(1, 1.0), (10, 5.0), (500, 10.0), (500, 100.0)] {
+            let points = random_points(&mut rng, n, extent);
+            let mut hash = hash(&points);
+
+            // Query points far outside the points too, so the search box has
+            // to grow many times.
+            for query in random_points(&mut rng, 100, 3.0 * extent) {
+                let expected = points
+                    .iter()
+                    .map(|q| q.distance_squared(query))
+                    .fold(f32::INFINITY, f32::min);
+                let nearest = hash.nearest(query).unwrap();
+                assert_eq!(nearest.distance_squared(query), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_without_points() {
+        assert_eq!(hash(&[]).nearest(Vec3::ZERO), None);
+        assert_eq!(hash(&[Vec3::ONE]).nearest(Vec3::NAN), None);
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{DELAYED, EMITTER, Particles, script_manager::ScriptManager};
+
+/// A sequence of scripts that are played one after the other.
+///
+/// The file passed with `--timeline` has a line per script with the number
+/// of seconds to play it for, followed by its path relative to the timeline
+/// file. Empty lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// # Play the spiral for 10 seconds, then the snow for 8
+/// 10 spiral.roto
+/// 8 snow.roto
+/// ```
+///
+/// The particles of a script are removed when the next one starts, and if
+/// the next script fails to compile nothing plays until it is fixed. After
+/// the last script, the timeline starts over with `--timeline-loop`.
+/// Otherwise the show stops: the particles are removed and the last script
+/// stops running, until it is edited.
+#[derive(Resource)]
+pub struct Timeline {
+    entries: Vec<TimelineEntry>,
+    looping: bool,
+    stopped: bool,
+    current: usize,
+    elapsed: f32,
+}
+
+struct TimelineEntry {
+    duration: f32,
+    script: PathBuf,
+}
+
+impl Timeline {
+    pub fn load(path: &Path, looping: bool) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        let mut entries = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (duration, script) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("expected a duration and a script: {line}"))?;
+            let duration: f32 = duration
+                .parse()
+                .map_err(|_| format!("invalid duration: {duration}"))?;
+            if duration <= 0.0 || duration.is_nan() {
+                return Err(format!("duration must be positive, got {duration}"));
+            }
+            entries.push(TimelineEntry {
+                duration,
+                script: dir.join(script.trim()),
+            });
+        }
+
+        if entries.is_empty() {
+            return Err("the timeline has no scripts".into());
+        }
+
+        Ok(Self {
+            entries,
+            looping,
+            stopped: false,
+            current: 0,
+            elapsed: 0.0,
+        })
+    }
+
+    /// The script to start with.
+    pub fn first_script(&self) -> &Path {
+        &self.entries[0].script
+    }
+}
+
+pub fn play_timeline(
+    time: Res<Time>,
+    timeline: Option<ResMut<Timeline>>,
+    mut manager: ResMut<ScriptManager>,
+    mut particles: Single<&mut Particles>,
+) {
+    let Some(mut timeline) = timeline else {
+        return;
+    };
+    if timeline.stopped {
+        return;
+    }
+
+    timeline.elapsed += time.delta_secs();
+    let duration = timeline.entries[timeline.current].duration;
+    if timeline.elapsed < duration {
+        return;
+    }
+
+    let next = timeline.current + 1;
+    let next = if next < timeline.entries.len() {
+        next
+    } else if timeline.looping {
+        0
+    } else {
+        timeline.stopped = true;
+        manager.stop();
+        clear_particles(&mut particles);
+        return;
+    };
+
+    timeline.elapsed -= duration;
+    timeline.current = next;
+    if timeline.entries.len() > 1 {
+        manager.switch_script(&timeline.entries[next].script);
+        clear_particles(&mut particles);
+    }
+}
+
+/// Remove the particles of the previous script, including the ones it
+/// queued.
+fn clear_particles(particles: &mut Particles) {
+    particles.0.clear();
+    EMITTER.lock().unwrap().clear();
+    DELAYED.lock().unwrap().clear();
+}